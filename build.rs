@@ -0,0 +1,14 @@
+use std::process::Command;
+
+/// Record the git hash of the source, if it is in a git repository, for the version output.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
+    if let Ok(output) = output {
+        if output.status.success() {
+            let git_hash = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=AISLOBSTERBLE_GIT_HASH={}", git_hash.trim());
+        }
+    }
+}
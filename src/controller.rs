@@ -1,63 +1,415 @@
 use std::cmp;
+use std::fmt;
+use std::ops::Range;
 use std::fs;
-use std::thread;
-use std::time::Duration;
-use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
 
-use log;
+use futures::future::{join_all, select_all};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use tokio::task;
+use tracing::Instrument;
 
-use crate::models::config_models::Config;
-use crate::models::game_models::{Axis, Coordinates, GameBoard, PlayedTile, Rack, Tile};
-use crate::models::serializers::{FlatPlayedTileSerializer, GameInfo, GameSerializer, PlayedTileSerializer, TileSerializer};
-use crate::slobsterble_client::{SlobsterbleClient};
-use crate::utilities::{next_combination, next_permutation};
+use crate::dictionary::Dawg;
+use crate::endgame;
+use crate::models::config_models::{Config, Difficulty};
+use crate::models::game_models::{leave_value, rack_balance, Axis, BoardLayout, Coordinates, CrossChecks, GameBoard, PlayedTile, Rack, Tile, TileBag};
+use crate::models::record_models::{GameRecord, TurnRecord};
+use crate::models::serializers::{FlatPlayedTileSerializer, GameInfo, GamePlayerInfo, GameSerializer, PrevMoveSerializer};
+use crate::slobsterble_client::{SlobsterbleClient, TurnError};
+use crate::utilities::{first_occurrences, next_combination};
 
 
 const PLAY_ATTEMPTS_LIMIT: u32 = 10;
-const BLANK_FILLERS: [char; 5] = ['S', 'E', 'R', 'A', 'T'];
+const MAX_CONSECUTIVE_PASSES: u32 = 2;
+const MAX_EXCHANGE_COUNT: usize = 7;
 
 pub struct Controller {
-    client: SlobsterbleClient,
+    /// Shared with the long polls that outlive a round of polling.
+    client: Arc<SlobsterbleClient>,
     config: Config,
-    dictionary: HashSet<String>,
+    /// The dictionary for games that do not name one of the configured dictionaries.
+    dictionary: Arc<Dawg>,
+    /// The configured dictionaries by lower case dictionary id.
+    dictionaries: HashMap<String, Arc<Dawg>>,
+    /// The words the server has rejected. These are not kept per dictionary, so a word rejected
+    /// in a game of one dictionary is not played in games of any other. They are shared with the
+    /// move generation tasks, and copied only when a word is added.
+    rejected_words: Arc<HashSet<String>>,
+    record: GameRecord,
+    rng: StdRng,
+    consecutive_passes: HashMap<String, u32>,
+    player_id: Option<i32>,
+    /// The turn number of each game when its state was last fetched and there was no turn to play.
+    idle_turns: HashMap<String, i32>,
+    active_game_count: usize,
+    turns_since_heartbeat: u32,
+    /// The turns played in each game, in the order they were played.
+    score_history: HashMap<String, Vec<TurnRecord>>,
+    /// The number of failed turns in a row in each game.
+    consecutive_failures: HashMap<String, u32>,
+    /// The number of polls left before each game with repeated failures is tried again.
+    failure_cooldowns: HashMap<String, u32>,
+    /// The board layout of each game, which does not change during the game.
+    board_layouts: HashMap<String, Arc<BoardLayout>>,
+    /// The ids of the completed games whose results have been logged.
+    reported_game_ids: HashSet<i32>,
+}
+
+/// A legal play with its score, the tiles left on the rack after making it, the value of the
+/// premium squares it opens to the opponent, and the words it forms.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Candidate {
+    played_tiles: Vec<PlayedTile>,
+    score: i32,
+    leave: Vec<Tile>,
+    exposure: i32,
+    words: Vec<String>,
+}
+
+/// The weights given to the leave and to the exposure of premium squares when ranking plays.
+#[derive(Clone, Copy, Debug)]
+struct RankWeights {
+    leave: f64,
+    defense: f64,
+}
+
+impl RankWeights {
+    fn new(config: &Config) -> RankWeights {
+        RankWeights{ leave: config.leave_weight, defense: config.defense_weight }
+    }
+}
+
+impl Candidate {
+    pub fn get_played_tiles_ref(&self) -> &Vec<PlayedTile> { &self.played_tiles }
+    pub fn get_score(&self) -> i32 { self.score }
+    pub fn get_words_ref(&self) -> &Vec<String> { &self.words }
+    pub fn get_leave_ref(&self) -> &Vec<Tile> { &self.leave }
+
+    /// Combine the score with the weighted value of the leave and the weighted exposure.
+    fn rank(&self, weights: RankWeights) -> f64 {
+        self.score as f64 + weights.leave * leave_value(&self.leave) as f64 - weights.defense * self.exposure as f64
+    }
+}
+
+/// The cost of choosing plays, accumulated over the turns taken in one poll.
+#[derive(Debug, Default)]
+struct PollStats {
+    turns: u32,
+    candidates: usize,
+    generation_time: Duration,
+    ranking_time: Duration,
+    largest_search: Option<(String, usize)>,
+}
+
+impl PollStats {
+    fn record_turn(&mut self, game_id: &str, candidate_count: usize, generation_time: Duration, ranking_time: Duration) {
+        self.turns += 1;
+        self.candidates += candidate_count;
+        self.generation_time += generation_time;
+        self.ranking_time += ranking_time;
+        if self.largest_search.as_ref().is_none_or(|(_game_id, largest_count)| candidate_count > *largest_count) {
+            self.largest_search = Some((String::from(game_id), candidate_count));
+        }
+    }
+}
+
+impl fmt::Display for PollStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "{} turns, {} candidates, {} ms generating, {} ms ranking.",
+            self.turns, self.candidates, self.generation_time.as_millis(), self.ranking_time.as_millis()
+        )?;
+        if let Some((game_id, candidate_count)) = &self.largest_search {
+            write!(f, " Largest search was {} candidates in game {}.", candidate_count, game_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// A periodic summary confirming that an AI player is still polling and can authenticate.
+struct Heartbeat {
+    active_games: usize,
+    turns: u32,
+    authenticated: bool,
+    uptime: Duration,
+}
+
+impl fmt::Display for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let uptime_seconds = self.uptime.as_secs();
+        write!(
+            f, "{} active games, {} turns since the last heartbeat, {}, up for {}h {}m {}s.",
+            self.active_games, self.turns, if self.authenticated { "authenticated" } else { "not authenticated" },
+            uptime_seconds / 3600, uptime_seconds / 60 % 60, uptime_seconds % 60
+        )
+    }
+}
+
+/// The long polls of the accounts whose games lists have not yet changed.
+///
+/// The polls are kept across rounds, so that a change to the games of one account is not missed
+/// while the games of another are being played. Each poll runs as its own task, so that one left
+/// waiting keeps making progress, and cannot hold the account's tokens while its games are played.
+#[derive(Default)]
+struct LongPolls {
+    waits: Vec<task::JoinHandle<(usize, Result<bool, reqwest::Error>)>>,
+    waiting_accounts: HashSet<usize>,
+}
+
+impl Drop for LongPolls {
+    fn drop(&mut self) {
+        for wait in self.waits.iter() {
+            wait.abort();
+        }
+    }
+}
+
+impl LongPolls {
+
+    /// Wait until the games list of any account changes or its wait ends, first starting a wait
+    /// for each account that is not already waiting.
+    ///
+    /// Return false if the server does not support long polling.
+    async fn wait_for_change(&mut self, controllers: &[Controller], wait_seconds: u64) -> Result<bool, reqwest::Error> {
+        for (index, controller) in controllers.iter().enumerate() {
+            if self.waiting_accounts.insert(index) {
+                let client = Arc::clone(&controller.client);
+                self.waits.push(task::spawn(async move { (index, client.wait_for_games(wait_seconds).await) }));
+            }
+        }
+        if self.waits.is_empty() {
+            return Ok(false);
+        }
+        let (finished, _, remaining) = select_all(std::mem::take(&mut self.waits)).await;
+        self.waits = remaining;
+        // The waits are only aborted when dropped, so a wait can only fail to finish by panicking.
+        let (index, result) = finished.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        self.waiting_accounts.remove(&index);
+        result
+    }
+}
+
+/// The fixed inputs and the results of a search for plays through a single anchor square.
+struct AnchorSearch<'a> {
+    dictionary: &'a Dawg,
+    rejected_words: &'a HashSet<String>,
+    game_board: &'a GameBoard,
+    cross_checks: &'a CrossChecks,
+    anchor: Coordinates,
+    axis: &'a Axis,
+    deadline: Option<Instant>,
+    candidates: Vec<Candidate>,
 }
 
 impl Controller {
 
-    pub fn new(config: Config) -> Controller {
-        let dictionary = load_dictionary();
-        Controller{ client: SlobsterbleClient::new(config.clone()), config, dictionary }
+    /// Create a controller for each configured account, sharing a single copy of each dictionary.
+    pub fn for_accounts(config: &Config) -> Result<Vec<Controller>, String> {
+        let dictionary = Arc::new(load_dictionary(&config.dictionary_path)?);
+        let dictionaries = config.dictionary_paths.iter()
+            .map(|(dictionary_id, path)| Ok((dictionary_id.clone(), Arc::new(load_dictionary(path)?))))
+            .collect::<Result<HashMap<String, Arc<Dawg>>, String>>()?;
+        config.accounts.iter()
+            .map(|account| Controller::new(config.for_account(account), Arc::clone(&dictionary), dictionaries.clone()))
+            .collect()
     }
 
-    fn poll(&mut self) {
-        log::debug!("Polling games.");
-        let games = match self.client.list_games() {
+    pub fn new(
+        config: Config, dictionary: Arc<Dawg>, dictionaries: HashMap<String, Arc<Dawg>>
+    ) -> Result<Controller, String> {
+        let rejected_words = match &config.rejected_words_path {
+            Some(path) => Arc::new(load_rejected_words(path)),
+            None => Arc::new(HashSet::new()),
+        };
+        let record = match &config.record_path {
+            Some(path) => GameRecord::load(path).unwrap_or_else(|e| {
+                tracing::error!("{}", e);
+                GameRecord::default()
+            }),
+            None => GameRecord::default(),
+        };
+        let rng = match config.opening_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let client = Arc::new(SlobsterbleClient::new(config.clone())?);
+        Ok(Controller{
+            client, config, dictionary, dictionaries, rejected_words, record,
+            rng, consecutive_passes: HashMap::new(), player_id: None, idle_turns: HashMap::new(),
+            active_game_count: 0, turns_since_heartbeat: 0, score_history: HashMap::new(),
+            consecutive_failures: HashMap::new(), failure_cooldowns: HashMap::new(), board_layouts: HashMap::new(),
+            reported_game_ids: HashSet::new(),
+        })
+    }
+
+    /// Check that the bot is ready to play by logging in, listing the games and checking that
+    /// every dictionary has words.
+    ///
+    /// Return a message saying the bot is ready, or the first problem found.
+    pub async fn self_test(&self) -> Result<String, String> {
+        let games = self.client.list_games().await
+            .map_err(|e| format!("Failed to list games for {}: {}", self.config.ai_display_name, e))?;
+        if !self.client.is_authenticated().await {
+            return Err(format!("Failed to log in as {}.", self.config.auth_data.username));
+        }
+        if self.dictionary.is_empty() {
+            return Err(format!("Dictionary {} has no words.", self.config.dictionary_path));
+        }
+        if let Some((dictionary_id, _dictionary)) = self.dictionaries.iter().find(|(_id, dictionary)| dictionary.is_empty()) {
+            return Err(format!("Dictionary {} has no words.", self.config.dictionary_path_for(Some(dictionary_id))));
+        }
+        Ok(format!("Ready to play as {} with {} games listed.", self.config.ai_display_name, games.len()))
+    }
+
+    async fn poll(&mut self) {
+        tracing::debug!("Polling games.");
+        let games = match self.client.list_games().await {
             Ok(games) => games,
             Err(e) => {
-                log::error!("Error fetching games list: {}", e);
+                tracing::error!("Error fetching games list: {}", e);
                 Vec::new()
             }
         };
-        let active_games = Controller::filter_active_games(games);
-        let potential_ai_turn_games = self.filter_by_ai_name(active_games);
-        for game in potential_ai_turn_games.into_iter() {
-            let game_state = match self.client.get_game(&game.id.to_string()) {
+        self.report_completed_games(&games);
+        self.record_completed_games(&games);
+        let active_games = self.filter_ignored(Controller::filter_active_games(games));
+        self.active_game_count = active_games.len();
+        let active_game_ids: HashSet<String> = active_games.iter().map(|game| game.id.to_string()).collect();
+        self.idle_turns.retain(|game_id, _turn_number| active_game_ids.contains(game_id));
+        self.consecutive_failures.retain(|game_id, _failures| active_game_ids.contains(game_id));
+        self.failure_cooldowns.retain(|game_id, _polls| active_game_ids.contains(game_id));
+        self.board_layouts.retain(|game_id, _layout| active_game_ids.contains(game_id));
+        let potential_ai_turn_games = self.filter_changed(self.filter_by_turn(active_games));
+        let game_ids: Vec<String> = potential_ai_turn_games.iter().map(|game| game.id.to_string()).collect();
+        let game_states = join_all(game_ids.iter().map(|game_id| self.client.get_game(game_id))).await;
+        let mut stats = PollStats::default();
+        for (game_id, game_state) in game_ids.iter().zip(game_states) {
+            let game_state = match game_state {
                 Ok(game_state) => game_state,
                 Err(e) => {
-                    log::error!("Error fetching game state for game {}: {}", &game.id, e);
+                    tracing::error!("Error fetching game state for game {}: {}", game_id, e);
                     continue;
                 },
             };
-            if Controller::is_ai_turn(&game_state) {
-                let game_board = GameBoard::new(&game_state);
-                let rack = Rack::new(&game_state);
-                match self.play_turn(&game.id.to_string(), game_board, rack) {
-                    Ok(_result_string) => log::debug!("Successfully played turn in game {}", &game.id),
-                    Err(result_string) => log::debug!("Failed to play turn in game {}: {}", &game.id, result_string),
+            self.player_id = Some(game_state.fetcher_player_id);
+            self.idle_turns.insert(game_id.clone(), game_state.turn_number);
+            if Controller::is_ai_turn(game_id, &game_state) {
+                if self.is_dead_game(game_id, &game_state) {
+                    tracing::debug!("Skipping game {} since every recent turn has been a pass.", game_id);
+                    continue;
+                }
+                if self.is_cooling_down(game_id) {
+                    tracing::debug!("Skipping game {} after repeated failed turns.", game_id);
+                    // The turn has not changed, so the game must still be fetched to count down.
+                    self.idle_turns.remove(game_id);
+                    continue;
+                }
+                self.idle_turns.remove(game_id);
+                let game_span = tracing::info_span!("game", game_id = %game_id, turn = game_state.turn_number);
+                match self.play_turn(game_id, &game_state, &mut stats).instrument(game_span).await {
+                    Ok(_result_string) => {
+                        tracing::debug!("Successfully played turn in game {}", game_id);
+                        self.consecutive_failures.remove(game_id);
+                    },
+                    Err(result_string) => {
+                        tracing::debug!("Failed to play turn in game {}: {}", game_id, result_string);
+                        self.record_failure(game_id);
+                    },
                 }
             }
         }
-        ()
+        self.turns_since_heartbeat += stats.turns;
+        if stats.turns > 0 {
+            tracing::info!("Poll stats for {}: {}", self.config.ai_display_name, stats);
+        }
+    }
+
+    /// Get the board layout of the game, building it from the game state the first time.
+    fn board_layout(&mut self, game_id: &String, game_state: &GameSerializer) -> Result<Arc<BoardLayout>, String> {
+        if let Some(layout) = self.board_layouts.get(game_id) {
+            return Ok(Arc::clone(layout));
+        }
+        let layout = BoardLayout::new(&game_state.board_layout)
+            .map_err(|e| format!("Invalid board layout in game {}: {}", game_id, e))?;
+        let layout = Arc::new(layout);
+        self.board_layouts.insert(game_id.clone(), Arc::clone(&layout));
+        Ok(layout)
+    }
+
+    /// Get the dictionary that the game is played with.
+    ///
+    /// Games that name no dictionary, or one that is not configured, use the default dictionary.
+    fn game_dictionary(&self, game_id: &str, game_state: &GameSerializer) -> Arc<Dawg> {
+        let dictionary_id = match &game_state.dictionary {
+            Some(dictionary_id) => dictionary_id,
+            None => return Arc::clone(&self.dictionary),
+        };
+        match self.dictionaries.get(&dictionary_id.to_lowercase()) {
+            Some(dictionary) => Arc::clone(dictionary),
+            None => {
+                tracing::warn!(
+                    "Game {} uses dictionary {}, which is not configured, so the default dictionary is used.",
+                    game_id, dictionary_id
+                );
+                Arc::clone(&self.dictionary)
+            },
+        }
+    }
+
+    /// Count a failed turn in the game.
+    ///
+    /// Once the failure threshold is reached a warning is logged and, if a cooldown is configured,
+    /// the game is skipped for that many polls. A game that fails again after its cooldown is
+    /// warned about and skipped again.
+    fn record_failure(&mut self, game_id: &String) {
+        let failures = self.consecutive_failures.entry(game_id.clone()).or_insert(0);
+        *failures += 1;
+        let threshold = match self.config.failure_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        if *failures < threshold {
+            return;
+        }
+        tracing::warn!("Failed to play a turn in game {} {} times in a row.", game_id, failures);
+        if let Some(cooldown_polls) = self.config.failure_cooldown_polls {
+            self.failure_cooldowns.insert(game_id.clone(), cooldown_polls);
+        }
+    }
+
+    /// Return true iff the game is being skipped after repeated failures, using up one poll of
+    /// its cooldown.
+    fn is_cooling_down(&mut self, game_id: &String) -> bool {
+        match self.failure_cooldowns.get_mut(game_id) {
+            Some(polls) if *polls > 0 => {
+                *polls -= 1;
+                true
+            },
+            Some(_polls) => {
+                self.failure_cooldowns.remove(game_id);
+                false
+            },
+            None => false,
+        }
+    }
+
+    /// Log a heartbeat summarizing the polls since the last heartbeat.
+    async fn log_heartbeat(&mut self, uptime: Duration) {
+        let heartbeat = Heartbeat{
+            active_games: self.active_game_count,
+            turns: self.turns_since_heartbeat,
+            authenticated: self.client.is_authenticated().await,
+            uptime,
+        };
+        tracing::info!("Heartbeat for {}: {}", self.config.ai_display_name, heartbeat);
+        self.turns_since_heartbeat = 0;
     }
 
     /// Filter a list of games down to those that are not completed.
@@ -65,22 +417,92 @@ impl Controller {
         games.into_iter().filter(|game| game.completed.is_none()).collect()
     }
 
-    /// Filter a list of games down to those whose turn name matches the configuration.
+    /// Filter out the games that the config says to ignore.
+    fn filter_ignored(&self, games: Vec<GameInfo>) -> Vec<GameInfo> {
+        games.into_iter().filter(|game| !self.config.ignored_game_ids.contains(&game.id)).collect()
+    }
+
+    /// Log the final scores and outcome of each completed game that has not been reported yet.
+    fn report_completed_games(&mut self, games: &[GameInfo]) {
+        for game in games.iter().filter(|game| game.completed.is_some()) {
+            if self.reported_game_ids.insert(game.id) {
+                tracing::info!("{}", describe_result(game, &self.config.ai_display_name));
+            }
+        }
+    }
+
+    /// Add any newly completed games to the win/loss record and log the updated record.
     ///
-    /// Due to a weakness of the API, this is not sufficient to identify games in which
-    /// it is the AI player's turn. The list_games API should be updated to provide a
-    /// unique identifier for the player whose turn it is. Nevertheless, for now this
-    /// will work in all cases where someone is not trying to test the limits and mimic
-    /// the display name of the AI player.
-    fn filter_by_ai_name(&self, games: Vec<GameInfo>) -> Vec<GameInfo> {
-        games.into_iter().filter(|game|
-            game.whose_turn_name.eq(&self.config.ai_display_name)
-        ).collect()
+    /// The AI player is identified by display name since the games list does not include
+    /// the id of the fetching player.
+    fn record_completed_games(&mut self, games: &[GameInfo]) {
+        let mut record_changed = false;
+        for game in games.iter().filter(|game| game.completed.is_some()) {
+            if self.record.is_recorded(game.id) {
+                continue;
+            }
+            let ai_score = match game.game_players.iter().find(|game_player|
+                game_player.player.display_name == self.config.ai_display_name
+            ) {
+                Some(game_player) => game_player.score,
+                None => continue,
+            };
+            let opponent_scores: Vec<i32> = game.game_players.iter()
+                .filter(|game_player| game_player.player.display_name != self.config.ai_display_name)
+                .map(|game_player| game_player.score)
+                .collect();
+            record_changed |= self.record.record(game.id, ai_score, &opponent_scores);
+        }
+        if !record_changed {
+            return;
+        }
+        tracing::info!("Record: {}", &self.record);
+        if let Some(path) = &self.config.record_path {
+            if let Err(e) = self.record.save(path) {
+                tracing::error!("{}", e);
+            }
+        }
+    }
+
+    /// Filter a list of games down to those in which it is the AI player's turn.
+    ///
+    /// This is done even if the server was asked to list only those games, since older servers
+    /// ignore the request.
+    ///
+    /// Once the AI player's id is known from a fetched game state, games that give the id of the
+    /// player whose turn it is are matched on that id. Other games are matched on the display name
+    /// of the player whose turn it is. That can be fooled by another player mimicking the AI
+    /// player's display name, so `is_ai_turn` checks again once the game state is fetched.
+    fn filter_by_turn(&self, games: Vec<GameInfo>) -> Vec<GameInfo> {
+        games.into_iter().filter(|game| match (game.whose_turn_id, self.player_id) {
+            (Some(whose_turn_id), Some(player_id)) => whose_turn_id == player_id,
+            (None, Some(player_id)) => game.game_players.iter().any(|game_player|
+                game_player.player.id == player_id && game_player.player.display_name == game.whose_turn_name
+            ),
+            (_, None) => game.whose_turn_name == self.config.ai_display_name,
+        }).collect()
+    }
+
+    /// Filter a list of games down to those that may have changed since there was last no turn to
+    /// play in them.
+    ///
+    /// Games that do not give their turn number are kept.
+    fn filter_changed(&self, games: Vec<GameInfo>) -> Vec<GameInfo> {
+        games.into_iter().filter(|game| match game.turn_number {
+            Some(turn_number) => self.idle_turns.get(&game.id.to_string()) != Some(&turn_number),
+            None => true,
+        }).collect()
     }
 
     /// Return True iff it is the AI player's turn.
-    fn is_ai_turn(game_state: &GameSerializer) -> bool {
+    ///
+    /// A game state without players is never the AI player's turn.
+    fn is_ai_turn(game_id: &str, game_state: &GameSerializer) -> bool {
         let num_players = game_state.game_players.len() as i32;
+        if num_players == 0 {
+            tracing::warn!("Game {} has no players, so it cannot be the AI player's turn.", game_id);
+            return false;
+        }
         let turn_order_to_match = game_state.turn_number % num_players;
         match game_state.game_players.iter().find(|game_player|
             game_player.turn_order == turn_order_to_match
@@ -90,74 +512,528 @@ impl Controller {
         }
     }
 
-    fn play_turn(&mut self, game_id: &String, game_board: GameBoard, rack: Rack) -> Result<String, String> {
-        log::debug!("Thinking...");
-        let mut candidates = self.candidate_plays(&game_board, &rack);
-        log::debug!("Determined candidates.");
-        candidates.sort_by_key(|pair| -pair.1);
-        let attempt_limit = cmp::min(candidates.len(), PLAY_ATTEMPTS_LIMIT as usize);
-        for (candidate_play, score) in candidates[..attempt_limit].iter() {
-            let mut serializable_play: Vec<FlatPlayedTileSerializer> = Vec::new();
-            for played_tile in candidate_play.iter() {
-                let row = played_tile.get_coordinates_ref().get_row();
-                let column = played_tile.get_coordinates_ref().get_column();
-                let letter_for_serializer = match played_tile.get_tile_ref().get_letter() {
-                    Some(letter) => Some(String::from(letter)),
-                    None => None,
-                };
-                let is_blank = played_tile.get_tile_ref().is_blank();
-                let value = played_tile.get_tile_ref().get_value();
-                let tile = TileSerializer{ letter: letter_for_serializer, is_blank, value };
-                let is_exchange = false;
-                let letter = played_tile.get_tile_ref().get_letter();
-                serializable_play.push(
-                    FlatPlayedTileSerializer{ is_blank, value, row, column, is_exchange, letter }
-                );
+    /// Return true iff the AI player has passed repeatedly and the last move was also a pass.
+    ///
+    /// Such a game can not make progress, so there is no use in generating moves for it.
+    fn is_dead_game(&self, game_id: &String, game_state: &GameSerializer) -> bool {
+        let passes = self.consecutive_passes.get(game_id).copied().unwrap_or(0);
+        passes >= MAX_CONSECUTIVE_PASSES && game_state.prev_move.as_ref().is_some_and(is_pass)
+    }
+
+    /// Forget the AI player's passes in the game if the last move was a play or an exchange.
+    ///
+    /// Only a run of passes with nothing else happening in between stalls the game, so the AI
+    /// player may pass again once the opponent has moved.
+    fn reset_passes_after_move(&mut self, game_id: &String, game_state: &GameSerializer) {
+        if game_state.prev_move.as_ref().is_some_and(|prev_move| !is_pass(prev_move)) {
+            self.consecutive_passes.remove(game_id);
+        }
+    }
+
+    /// Return true iff the AI player leads by the configured close out margin late in the game.
+    fn should_close_out(&self, game_state: &GameSerializer) -> bool {
+        let close_out_margin = match self.config.close_out_margin {
+            Some(close_out_margin) => close_out_margin,
+            None => return false,
+        };
+        if game_state.num_tiles_remaining > self.config.close_out_tiles_remaining {
+            return false;
+        }
+        score_lead(game_state).is_some_and(|lead| lead >= close_out_margin)
+    }
+
+    /// Return true iff the AI player trails by the configured resign margin late in the game.
+    fn should_resign(&self, game_state: &GameSerializer) -> bool {
+        let resign_margin = match self.config.resign_margin {
+            Some(resign_margin) => resign_margin,
+            None => return false,
+        };
+        if game_state.num_tiles_remaining > self.config.resign_tiles_remaining {
+            return false;
+        }
+        score_lead(game_state).is_some_and(|lead| -lead >= resign_margin)
+    }
+
+    /// Return true iff the best play scores less than the configured minimum play score, or
+    /// exchanging is better than the best play by the configured exchange margin.
+    ///
+    /// The play is worth its score plus the value of its leave. The exchange is worth the value of
+    /// the tiles it keeps, since nothing is known about the tiles it will draw.
+    fn should_exchange(&self, game_state: &GameSerializer, rack: &Rack, best_candidate: &Candidate) -> bool {
+        if game_state.num_tiles_remaining < rack.tiles.len() as i32 {
+            return false;
+        }
+        if self.config.min_play_score.is_some_and(|min_play_score| best_candidate.score < min_play_score) {
+            return true;
+        }
+        let exchange_margin = match self.config.exchange_margin {
+            Some(exchange_margin) => exchange_margin,
+            None => return false,
+        };
+        let mut kept_tiles = rack.tiles.clone();
+        for tile in Controller::exchange_selection(rack) {
+            if let Some(position) = kept_tiles.iter().position(|kept_tile| *kept_tile == tile) {
+                kept_tiles.remove(position);
             }
-            match self.client.play_turn(game_id, &serializable_play) {
+        }
+        let play_value = best_candidate.score + leave_value(&best_candidate.leave);
+        leave_value(&kept_tiles) - play_value >= exchange_margin
+    }
+
+    /// Sort candidates to close out the game rather than maximize score.
+    ///
+    /// Plays that use more tiles bring the game closer to an end, so they rank first. Ties
+    /// are broken by leaving fewer open squares next to the play and then by score.
+    fn sort_for_close_out(candidates: &mut [Candidate], game_board: &GameBoard) {
+        candidates.sort_by_key(|candidate| (
+            cmp::Reverse(candidate.played_tiles.len()),
+            game_board.open_neighbour_count(&candidate.played_tiles),
+            cmp::Reverse(candidate.score),
+        ));
+    }
+
+    /// Sort candidates for the endgame, once the opponent's rack is known.
+    ///
+    /// Plays that go out rank first, since they end the game before the opponent can reply.
+    /// Ties are broken by the difference each play makes to the final margin.
+    fn sort_for_endgame(candidates: &mut [Candidate], opponent_rack: &[Tile]) {
+        candidates.sort_by_key(|candidate| (
+            !candidate.leave.is_empty(),
+            cmp::Reverse(endgame::spread(candidate.score, &candidate.leave, opponent_rack)),
+        ));
+    }
+
+    async fn play_turn(
+        &mut self, game_id: &String, game_state: &GameSerializer, stats: &mut PollStats
+    ) -> Result<String, String> {
+        self.reset_passes_after_move(game_id, game_state);
+        if self.should_resign(game_state) {
+            return self.resign(game_id).await;
+        }
+        let layout = self.board_layout(game_id, game_state)?;
+        let game_board = GameBoard::with_layout(layout, game_state)
+            .map_err(|e| format!("Invalid board in game {}: {}", game_id, e))?;
+        let rack = Rack::new(game_state);
+        tracing::debug!("Board for game {}:\n{}\nRack: {}", game_id, game_board, rack);
+        let tile_bag = TileBag::new(game_state);
+        if !tile_bag.is_empty() {
+            let unseen_tiles = tile_bag.unseen_tiles(&game_board, &rack);
+            let unseen_letters: Vec<String> = unseen_tiles.iter().map(|tile| tile.to_string()).collect();
+            tracing::debug!("Unseen tiles: {}", unseen_letters.join(" "));
+        }
+        tracing::debug!("Thinking...");
+        let generation_start = Instant::now();
+        let dictionary = self.game_dictionary(game_id, game_state);
+        let rejected_words = Arc::clone(&self.rejected_words);
+        let parallel = self.config.parallel_move_generation;
+        let deadline = self.config.max_think_millis
+            .map(|max_think_millis| generation_start + Duration::from_millis(max_think_millis));
+        let (dictionary, game_board, rack, mut candidates) = task::spawn_blocking(move || {
+            let candidates = candidate_plays(&dictionary, &rejected_words, parallel, &game_board, &rack, deadline);
+            (dictionary, game_board, rack, candidates)
+        }).await.map_err(|e| format!("Failed to generate moves in game {}: {}", game_id, e))?;
+        let generation_time = generation_start.elapsed();
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            tracing::warn!("Ran out of thinking time in game {}, so the search was cut short.", game_id);
+        }
+        tracing::info!(
+            "Determined {} candidates in {} ms with {} move generation.", candidates.len(),
+            generation_time.as_millis(),
+            if self.config.parallel_move_generation { "parallel" } else { "single-threaded" }
+        );
+        if candidates.is_empty() {
+            stats.record_turn(game_id, 0, generation_time, Duration::ZERO);
+            if game_state.num_tiles_remaining >= rack.tiles.len() as i32 {
+                return self.exchange_tiles(game_id, game_state.turn_number, &rack).await;
+            }
+            return self.pass_turn(game_id, game_state.turn_number).await;
+        }
+        let ranking_start = Instant::now();
+        Controller::sort_by_rank(&mut candidates, RankWeights::new(&self.config));
+        if let Some(opponent_rack) = endgame::opponent_rack(game_state, &game_board, &rack) {
+            tracing::debug!("Playing the endgame in game {} against a rack of {} tiles.", game_id, opponent_rack.len());
+            Controller::sort_for_endgame(&mut candidates, &opponent_rack);
+            if let Some(lookahead_candidates) = self.config.lookahead_candidates {
+                self.look_ahead(dictionary, &game_board, &mut candidates, opponent_rack, lookahead_candidates, deadline)
+                    .await.map_err(|e| format!("Failed to look ahead in game {}: {}", game_id, e))?;
+            }
+        } else if self.should_close_out(game_state) {
+            tracing::debug!("Playing to close out game {}.", game_id);
+            Controller::sort_for_close_out(&mut candidates, &game_board);
+        } else if self.should_exchange(game_state, &rack, &candidates[0]) {
+            tracing::info!(
+                "Exchanging in game {} rather than playing {}.", game_id, describe_play(&candidates[0])
+            );
+            stats.record_turn(game_id, candidates.len(), generation_time, ranking_start.elapsed());
+            return self.exchange_tiles(game_id, game_state.turn_number, &rack).await;
+        }
+        if self.config.randomize_opening && game_board.is_empty() {
+            Controller::choose_tied_best(&mut candidates, RankWeights::new(&self.config), &mut self.rng);
+        }
+        Controller::choose_for_difficulty(&mut candidates, self.config.difficulty, &mut self.rng);
+        let ranking_time = ranking_start.elapsed();
+        tracing::info!("Ranked {} candidates in {} ms.", candidates.len(), ranking_time.as_millis());
+        stats.record_turn(game_id, candidates.len(), generation_time, ranking_time);
+        if self.config.dry_run {
+            let dry_run_message = format!(
+                "Dry run in game {}: would play {}.", game_id, describe_play(&candidates[0])
+            );
+            tracing::info!("{}", &dry_run_message);
+            return Ok(dry_run_message);
+        }
+        let attempt_limit = cmp::min(candidates.len(), PLAY_ATTEMPTS_LIMIT as usize);
+        for candidate in candidates[..attempt_limit].iter() {
+            let serializable_play = serialize_play(&candidate.played_tiles);
+            match self.play_candidate(game_id, game_state, &serializable_play).await {
                 Ok(_response) => {
+                    self.consecutive_passes.remove(game_id);
+                    let played_message = format!(
+                        "Played {} for {} points in game {}.", candidate.words.join(", "), candidate.score, game_id
+                    );
+                    tracing::info!("{}", &played_message);
+                    // Checking the score records the turn as reported by the server.
                     if self.config.check_score {
-                        match self.verify_score(game_id, &serializable_play, *score) {
-                            Ok(msg) => {
-                                log::info!("{}", &msg);
-                                return Ok(msg);
-                            },
-                            Err(err) => {
-                                log::error!("{}", err);
-                                return Ok(String::from("Error verifying score."));
-                            },
+                        match self.verify_score(game_id, game_state, &serializable_play, candidate.score).await {
+                            Ok(msg) => tracing::info!("{}", &msg),
+                            // The turn has been played, so a mismatch is not a failed turn.
+                            Err(err) => tracing::error!("{}", err),
                         }
                     } else {
-                        let success_message = format!("Successfully played turn in game {}.", game_id);
-                        log::info!("{}", &success_message);
-                        return Ok(success_message);
+                        let word = candidate.words.first().cloned();
+                        self.record_turn(game_id, game_state.turn_number, word, candidate.score);
                     }
+                    return Ok(played_message);
                 },
                 Err(err) => {
                     let error_message = format!(
                         "Error submitting turn {:?} to game {}. Error: {}",
                         &serializable_play, game_id, err
                     );
-                    log::error!("{}", &error_message);
+                    tracing::error!("{}", &error_message);
+                    match &err {
+                        TurnError::Rejected { reason, .. } => {
+                            self.handle_rejected_play(
+                                game_state.dictionary.as_deref(), &game_board, &candidate.played_tiles, reason
+                            );
+                        },
+                        // The play has already been retried where that is safe, so rather than
+                        // fall back to a worse play, leave the game to the next poll.
+                        TurnError::Request(_) => return Err(error_message),
+                    }
                 },
             };
         }
         Err(format!("Failed to successfully play a turn in game {}.", game_id))
     }
 
+    /// Submit a play, trying once more if it fails in a way that may be temporary.
+    ///
+    /// The play may have been made even though its response was lost, so before trying again the
+    /// game is fetched, and if its turn has moved on the play is taken to have been made.
+    async fn play_candidate(
+        &self, game_id: &str, game_state: &GameSerializer, serializable_play: &Vec<FlatPlayedTileSerializer>
+    ) -> Result<String, TurnError> {
+        let err = match self.client.play_turn(game_id, serializable_play).await {
+            Err(err) if err.is_retryable() => err,
+            result => return result,
+        };
+        tracing::warn!("Failed to submit turn to game {}, checking the game before trying again: {}", game_id, err);
+        match self.client.get_game(game_id).await {
+            Ok(current_state) if current_state.turn_number != game_state.turn_number => {
+                tracing::info!("The turn in game {} was made despite the error.", game_id);
+                Ok(String::new())
+            },
+            Ok(_current_state) => self.client.play_turn(game_id, serializable_play).await,
+            Err(_) => Err(err),
+        }
+    }
+
+    /// Play the letters from the rack along the axis from the start square, regardless of whether
+    /// the play is legal or would be chosen, and compare the local score with the server's.
+    ///
+    /// Upper case letters take lettered tiles from the rack and lower case letters take blanks.
+    pub async fn force_play(
+        &mut self, game_id: &String, start: Coordinates, axis: Axis, letters: &str
+    ) -> Result<String, String> {
+        let game_state = self.client.get_game(game_id).await
+            .map_err(|e| format!("Failed to get game state in game {}: {}", game_id, e))?;
+        let game_board = GameBoard::new(&game_state)
+            .map_err(|e| format!("Invalid board layout in game {}: {}", game_id, e))?;
+        let tiles = tiles_from_rack(&Rack::new(&game_state), letters)?;
+        let played_tiles = game_board.build_played_tiles(&start, tiles.iter().collect(), &axis)
+            .map_err(|e| format!("Cannot play {} in game {}: {}", letters, game_id, e))?;
+        let (words, score) = game_board.words_and_score(&played_tiles)
+            .map_err(|e| format!("Cannot play {} in game {}: {}", letters, game_id, e))?;
+        let candidate = Candidate{ played_tiles, score, leave: Vec::new(), exposure: 0, words };
+        tracing::info!("Forcing play in game {}: {}.", game_id, describe_play(&candidate));
+        let serializable_play = serialize_play(&candidate.played_tiles);
+        self.client.play_turn(game_id, &serializable_play).await
+            .map_err(|e| format!("Error submitting turn {:?} to game {}. Error: {}", &serializable_play, game_id, e))?;
+        self.verify_score(game_id, &game_state, &serializable_play, score).await
+    }
+
+    /// Reorder the leading candidates by the margin they keep after the opponent's best reply.
+    ///
+    /// This is a search of two turns, so it is only done once the opponent's rack is known.
+    async fn look_ahead(
+        &self, dictionary: Arc<Dawg>, game_board: &GameBoard, candidates: &mut [Candidate], opponent_rack: Vec<Tile>,
+        lookahead_candidates: usize, deadline: Option<Instant>
+    ) -> Result<(), task::JoinError> {
+        let lookahead_count = cmp::min(lookahead_candidates, candidates.len());
+        let rejected_words = Arc::clone(&self.rejected_words);
+        let parallel = self.config.parallel_move_generation;
+        let game_board = game_board.clone();
+        let mut lookahead = candidates[..lookahead_count].to_vec();
+        let lookahead = task::spawn_blocking(move || {
+            sort_by_reply(&dictionary, &rejected_words, parallel, &game_board, &mut lookahead, &opponent_rack, deadline);
+            lookahead
+        }).await?;
+        candidates[..lookahead_count].clone_from_slice(&lookahead);
+        Ok(())
+    }
+
+    /// Exchange tiles from the rack when there is no play available.
+    async fn exchange_tiles(&mut self, game_id: &String, turn_number: i32, rack: &Rack) -> Result<String, String> {
+        let exchanged_tiles: Vec<FlatPlayedTileSerializer> = Controller::exchange_selection(rack)
+            .iter()
+            .map(|tile| FlatPlayedTileSerializer{
+                is_blank: tile.is_blank(),
+                value: tile.get_value(),
+                row: -1,
+                column: -1,
+                is_exchange: true,
+                letter: tile.get_letter(),
+            })
+            .collect();
+        if self.config.dry_run {
+            let dry_run_message = format!(
+                "Dry run in game {}: would exchange {} tiles.", game_id, exchanged_tiles.len()
+            );
+            tracing::info!("{}", &dry_run_message);
+            return Ok(dry_run_message);
+        }
+        match self.client.exchange_tiles(game_id, &exchanged_tiles).await {
+            Ok(_response) => {
+                self.consecutive_passes.remove(game_id);
+                self.record_turn(game_id, turn_number, None, 0);
+                let success_message = format!(
+                    "Successfully exchanged {} tiles in game {}.", exchanged_tiles.len(), game_id
+                );
+                tracing::info!("{}", &success_message);
+                Ok(success_message)
+            },
+            Err(err) => {
+                let error_message = format!(
+                    "Error exchanging tiles {:?} in game {}. Error: {}", &exchanged_tiles, game_id, err
+                );
+                tracing::error!("{}", &error_message);
+                Err(error_message)
+            },
+        }
+    }
+
+    /// Pass the turn when there is neither a play nor an exchange available.
+    async fn pass_turn(&mut self, game_id: &String, turn_number: i32) -> Result<String, String> {
+        let passes = self.consecutive_passes.get(game_id).copied().unwrap_or(0);
+        if passes >= MAX_CONSECUTIVE_PASSES {
+            return Err(format!("Already passed {} turns in a row in game {}.", passes, game_id));
+        }
+        if self.config.dry_run {
+            let dry_run_message = format!("Dry run in game {}: would pass.", game_id);
+            tracing::info!("{}", &dry_run_message);
+            return Ok(dry_run_message);
+        }
+        match self.client.pass_turn(game_id).await {
+            Ok(_response) => {
+                self.consecutive_passes.insert(game_id.clone(), passes + 1);
+                self.record_turn(game_id, turn_number, None, 0);
+                let success_message = format!("Successfully passed in game {}.", game_id);
+                tracing::info!("{}", &success_message);
+                Ok(success_message)
+            },
+            Err(err) => {
+                let error_message = format!("Error passing in game {}. Error: {}", game_id, err);
+                tracing::error!("{}", &error_message);
+                Err(error_message)
+            },
+        }
+    }
+
+    async fn resign(&mut self, game_id: &String) -> Result<String, String> {
+        if self.config.dry_run {
+            let dry_run_message = format!("Dry run in game {}: would resign.", game_id);
+            tracing::info!("{}", &dry_run_message);
+            return Ok(dry_run_message);
+        }
+        match self.client.resign(game_id).await {
+            Ok(_response) => {
+                let success_message = format!("Resigned from game {}.", game_id);
+                tracing::info!("{}", &success_message);
+                Ok(success_message)
+            },
+            Err(err) => {
+                let error_message = format!("Error resigning from game {}. Error: {}", game_id, err);
+                tracing::error!("{}", &error_message);
+                Err(error_message)
+            },
+        }
+    }
+
+    /// Choose the tiles to exchange so that the tiles kept have the best leave value.
+    ///
+    /// Every selection of at most `MAX_EXCHANGE_COUNT` tiles is tried, and of the selections that
+    /// keep equally good leaves the one exchanging the fewest tiles wins. The chosen tiles are
+    /// ordered from most to least worth getting rid of: high value consonants, the other
+    /// consonants, and then vowels.
+    fn exchange_selection(rack: &Rack) -> Vec<Tile> {
+        let rack_size = rack.tiles.len();
+        let mut best: Option<(i32, Vec<usize>)> = None;
+        for exchange_count in 1..=cmp::min(rack_size, MAX_EXCHANGE_COUNT) {
+            let mut selection = Some((0..exchange_count).collect::<Vec<usize>>());
+            while let Some(indices) = selection {
+                let kept_tiles: Vec<Tile> = rack.tiles.iter().enumerate()
+                    .filter(|(index, _tile)| !indices.contains(index))
+                    .map(|(_index, tile)| *tile)
+                    .collect();
+                let kept_value = leave_value(&kept_tiles);
+                if best.as_ref().is_none_or(|(best_value, _indices)| kept_value > *best_value) {
+                    best = Some((kept_value, indices.clone()));
+                }
+                selection = next_combination(indices, rack_size);
+            }
+        }
+        let mut selection: Vec<Tile> = match best {
+            Some((_kept_value, indices)) => indices.iter().map(|index| rack.tiles[*index]).collect(),
+            None => Vec::new(),
+        };
+        selection.sort_by_key(|tile| (tile.is_vowel(), cmp::Reverse(tile.get_value())));
+        selection
+    }
+
+    /// Sort candidates by descending score plus weighted leave value.
+    ///
+    /// Candidates whose ranks round to the same number of points are sorted by the balance of
+    /// their leaves first.
+    ///
+    /// The rank and leave balance of each candidate are computed once, before sorting.
+    fn sort_by_rank(candidates: &mut [Candidate], weights: RankWeights) {
+        let keys: Vec<(f64, f64)> = candidates.iter()
+            .map(|candidate| (candidate.rank(weights), rack_balance(&candidate.leave)))
+            .collect();
+        let mut order: Vec<usize> = (0..candidates.len()).collect();
+        order.sort_by(|first, second| {
+            let (first_rank, first_balance) = keys[*first];
+            let (second_rank, second_balance) = keys[*second];
+            second_rank.round().total_cmp(&first_rank.round())
+                .then(second_balance.total_cmp(&first_balance))
+                .then(second_rank.total_cmp(&first_rank))
+        });
+        let sorted: Vec<Candidate> = order.iter().map(|index| candidates[*index].clone()).collect();
+        candidates.clone_from_slice(&sorted);
+    }
+
+    /// Move a randomly chosen candidate from those tied for the best rank and leave balance to the
+    /// front.
+    ///
+    /// The candidates must already be sorted with `sort_by_rank`.
+    fn choose_tied_best(candidates: &mut [Candidate], weights: RankWeights, rng: &mut StdRng) {
+        let (best_rank, best_balance) = match candidates.first() {
+            Some(candidate) => (candidate.rank(weights), rack_balance(&candidate.leave)),
+            None => return,
+        };
+        let tied_count = candidates.iter()
+            .take_while(|candidate| candidate.rank(weights) == best_rank && rack_balance(&candidate.leave) == best_balance)
+            .count();
+        let chosen_index = rng.gen_range(0..tied_count);
+        candidates.swap(0, chosen_index);
+    }
+
+    /// Move a play chosen for the difficulty to the front of the ranked candidates.
+    fn choose_for_difficulty(candidates: &mut [Candidate], difficulty: Difficulty, rng: &mut StdRng) {
+        let choice_range = difficulty_choice_range(difficulty, candidates.len());
+        if choice_range.is_empty() {
+            return;
+        }
+        let chosen_index = rng.gen_range(choice_range);
+        candidates.swap(0, chosen_index);
+    }
+
+    /// Record the words formed by a play that the server rejected so that they are not played
+    /// again this session, and also in the rejected words file if one is configured.
+    ///
+    /// The rejected words are those formed by the play that the server's reason mentions. If the
+    /// reason mentions none of them, a word is only recorded when the play formed exactly one word.
+    fn handle_rejected_play(
+        &mut self, dictionary_id: Option<&str>, game_board: &GameBoard, played_tiles: &[PlayedTile], reason: &str
+    ) {
+        let words_created = match game_board.words_created(played_tiles) {
+            Ok(words_created) => words_created,
+            Err(_) => return,
+        };
+        let mut rejected_words: Vec<String> = words_created.iter()
+            .filter(|word| reason.split(|c: char| !c.is_alphabetic()).any(|token| token.eq_ignore_ascii_case(word)))
+            .cloned()
+            .collect();
+        if rejected_words.is_empty() && words_created.len() == 1 {
+            rejected_words = words_created;
+        }
+        for word in rejected_words {
+            tracing::warn!(
+                "The server rejected {}, so it will not be played again. Consider removing it from {}.",
+                word, self.config.dictionary_path_for(dictionary_id)
+            );
+            if let Some(path) = &self.config.rejected_words_path {
+                if let Err(e) = record_rejected_word(path, &word) {
+                    tracing::error!("Failed to record rejected word {}: {}", word, e);
+                }
+            }
+            Arc::make_mut(&mut self.rejected_words).insert(word);
+        }
+    }
+
+    /// Get the turns this controller has played in the game, in the order they were played.
+    pub fn score_history(&self, game_id: &str) -> &[TurnRecord] {
+        self.score_history.get(game_id).map_or(&[], |turns| turns.as_slice())
+    }
+
+    /// Add a turn to the score history of the game, appending it to the history file if one is configured.
+    fn record_turn(&mut self, game_id: &str, turn_number: i32, word: Option<String>, score: i32) {
+        let turn = TurnRecord{ game_id: String::from(game_id), turn_number, word, score };
+        if let Some(path) = &self.config.score_history_path {
+            if let Err(e) = turn.append(path) {
+                tracing::error!("{}", e);
+            }
+        }
+        self.score_history.entry(String::from(game_id)).or_default().push(turn);
+    }
+
     /// Verify that the score calculated by AISlobsterble matches that calculated by Slobsterble.
-    fn verify_score(
-        &mut self, game_id: &String, played_tiles: &Vec<FlatPlayedTileSerializer>, expected_score: i32
+    ///
+    /// The turn reported by the server is added to the score history of the game. If the scores
+    /// differ, the game state from before the play is saved to the score mismatch directory.
+    async fn verify_score(
+        &mut self, game_id: &String, game_state: &GameSerializer, played_tiles: &Vec<FlatPlayedTileSerializer>,
+        expected_score: i32
     ) -> Result<String, String> {
-        match self.client.get_game(game_id) {
+        match self.client.get_game(game_id).await {
             Ok(after_play_game_state) => {
                 let prev_move = after_play_game_state.prev_move;
                 match prev_move {
                     Some(prev_move) => {
+                        self.record_turn(
+                            game_id, after_play_game_state.turn_number - 1, prev_move.word.clone(), prev_move.score
+                        );
                         if prev_move.score != expected_score {
+                            let saved_to = match &self.config.score_mismatch_dir {
+                                Some(dir) => match save_game_state(dir, game_id, game_state) {
+                                    Ok(path) => format!(" Saved the game state to {}.", path),
+                                    Err(e) => format!(" {}", e),
+                                },
+                                None => String::new(),
+                            };
                             Err(format!(
-                                "Expected score {} but got score {} in game {} with tiles {:?}",
-                                expected_score, prev_move.score, game_id, &played_tiles
+                                "Score mismatch: expected score {} but got score {} in game {} with tiles {:?}.{}",
+                                expected_score, prev_move.score, game_id, &played_tiles, saved_to
                             ))
                         } else {
                             Ok(format!(
@@ -180,107 +1056,1523 @@ impl Controller {
         }
     }
 
-    fn candidate_plays(&self, game_board: &GameBoard, rack: &Rack) -> Vec<(Vec<PlayedTile>, i32)> {
-        if rack.tiles.iter().any(|tile| tile.is_letterless()) {
-            let mut candidates: Vec<(Vec<PlayedTile>, i32)> = Vec::new();
-            let letterless_count = rack.tiles.iter().filter(|tile| tile.is_letterless()).count();
-            if letterless_count == 1 {
-                for ch in b'A'..=b'Z' {
-                    let ch = ch as char;
-                    let filled_rack = rack.fill_blanks(&vec![ch]);
-                    log::debug!("{:?}", &filled_rack.tiles);
-                    candidates.extend(self.candidate_plays(game_board, &filled_rack));
+    /// Poll the games of each account in turn, sleeping for the poll interval between rounds.
+    ///
+    /// Each sleep is varied randomly by up to the jitter percentage of the poll interval. If a
+    /// number of heartbeat polls is given, each account logs a heartbeat after that many rounds.
+    ///
+    /// If a long poll wait is given, rounds instead wait for the games list of any account to
+    /// change, until the server is found not to support long polling.
+    pub async fn run(
+        controllers: &mut [Controller], poll_interval_seconds: u32, poll_jitter_percent: u32,
+        heartbeat_polls: Option<u32>, mut long_poll_seconds: Option<u64>
+    ) {
+        let sleep_duration = Duration::from_secs(poll_interval_seconds as u64);
+        let mut rng = StdRng::from_entropy();
+        let start = Instant::now();
+        let mut poll_count: u32 = 0;
+        let mut long_polls = LongPolls::default();
+        loop {
+            for controller in controllers.iter_mut() {
+                tracing::debug!("Polling games for {}.", controller.config.ai_display_name);
+                let poll_span = tracing::info_span!("poll", account = %controller.config.ai_display_name, poll = poll_count);
+                controller.poll().instrument(poll_span).await;
+            }
+            poll_count = poll_count.wrapping_add(1);
+            if heartbeat_polls.is_some_and(|heartbeat_polls| poll_count.is_multiple_of(heartbeat_polls)) {
+                for controller in controllers.iter_mut() {
+                    controller.log_heartbeat(start.elapsed()).await;
                 }
-                return candidates;
-            } else {
-                let mut letter_fills: Vec<char> = Vec::new();
-                for index in 0..letterless_count - 2 {
-                    letter_fills.push(BLANK_FILLERS[index % BLANK_FILLERS.len()]);
+            }
+            if let Some(wait_seconds) = long_poll_seconds {
+                match long_polls.wait_for_change(controllers, wait_seconds).await {
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        tracing::info!(
+                            "The server does not support long polling, so polling every {} seconds instead.",
+                            poll_interval_seconds
+                        );
+                        long_poll_seconds = None;
+                        long_polls = LongPolls::default();
+                    },
+                    Err(e) => tracing::error!("Error waiting for the games list to change: {}", e),
                 }
-                letter_fills.push('A');
-                letter_fills.push('A');
-                for ch_1 in b'A'..=b'Z' {
-                    let ch_1 = ch_1 as char;
-
-                    letter_fills[letterless_count - 2] = ch_1;
-                    for ch_2 in b'A'..=b'Z' {
-                        let ch_2 = ch_2 as char;
-                        letter_fills[letterless_count - 1] = ch_2;
-                        let filled_rack = rack.fill_blanks(&letter_fills);
-                        candidates.extend(self.candidate_plays(game_board, &filled_rack));
-                    }
+            }
+            tokio::time::sleep(jittered(sleep_duration, poll_jitter_percent, &mut rng)).await;
+        }
+    }
+}
+
+/// Vary the duration by a random amount of up to the percentage of it in either direction.
+fn jittered<R: Rng>(duration: Duration, jitter_percent: u32, rng: &mut R) -> Duration {
+    if jitter_percent == 0 {
+        return duration;
+    }
+    let jitter = jitter_percent as f64 / 100.0;
+    duration.mul_f64(rng.gen_range(1.0 - jitter..=1.0 + jitter))
+}
+
+/// Generate every legal play of tiles from the rack, skipping any that form a rejected word.
+///
+/// If the deadline passes, the plays found so far are returned. This is CPU bound, so callers on
+/// the async runtime should run it on the blocking pool.
+fn candidate_plays(
+    dictionary: &Dawg, rejected_words: &HashSet<String>, parallel: bool, game_board: &GameBoard, rack: &Rack,
+    deadline: Option<Instant>
+) -> Vec<Candidate> {
+    let anchors = game_board.anchors();
+    let cross_checks: Vec<(&Axis, CrossChecks)> = Axis::iterator()
+        .map(|axis| (axis, game_board.cross_checks(dictionary, axis)))
+        .collect();
+    let searches: Vec<(&Axis, &CrossChecks, &Coordinates)> = cross_checks.iter()
+        .flat_map(|(axis, cross_checks)| anchors.iter().map(move |anchor| (*axis, cross_checks, anchor)))
+        .collect();
+    let generate = |(axis, cross_checks, anchor): &(&Axis, &CrossChecks, &Coordinates)| {
+        let mut search = AnchorSearch{
+            dictionary, rejected_words, game_board, cross_checks, anchor: **anchor, axis, deadline,
+            candidates: Vec::new()
+        };
+        search.generate_moves_at_anchor(rack, &anchors);
+        search.candidates
+    };
+    let mut candidates: Vec<Candidate> = if parallel {
+        searches.par_iter().flat_map_iter(generate).collect()
+    } else {
+        searches.iter().flat_map(generate).collect()
+    };
+    // A single tile forming words on both axes is found from both axes.
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Return true iff the move was a pass, forming no word and exchanging no tiles.
+fn is_pass(prev_move: &PrevMoveSerializer) -> bool {
+    prev_move.word.is_none() && prev_move.exchanged_count == 0
+}
+
+/// Get how far the fetching player's score is ahead of the best opponent score, which is negative
+/// when behind.
+///
+/// Return None if the fetching player is not in the game.
+fn score_lead(game_state: &GameSerializer) -> Option<i32> {
+    let ai_score = game_state.game_players.iter()
+        .find(|game_player| game_player.player.id == game_state.fetcher_player_id)?
+        .score;
+    let best_opponent_score = game_state.game_players.iter()
+        .filter(|game_player| game_player.player.id != game_state.fetcher_player_id)
+        .map(|game_player| game_player.score)
+        .max()
+        .unwrap_or(0);
+    Some(ai_score - best_opponent_score)
+}
+
+/// Convert the played tiles to the form the server expects for a play.
+fn serialize_play(played_tiles: &[PlayedTile]) -> Vec<FlatPlayedTileSerializer> {
+    played_tiles.iter()
+        .map(|played_tile| {
+            let tile = played_tile.get_tile_ref();
+            FlatPlayedTileSerializer{
+                is_blank: tile.is_blank(),
+                value: tile.get_value(),
+                row: played_tile.get_coordinates_ref().get_row(),
+                column: played_tile.get_coordinates_ref().get_column(),
+                is_exchange: false,
+                letter: tile.get_letter(),
+            }
+        })
+        .collect()
+}
+
+/// Take a tile from the rack for each letter, in order.
+///
+/// Upper case letters take a lettered tile and lower case letters take a blank, which is
+/// assigned the upper case letter.
+fn tiles_from_rack(rack: &Rack, letters: &str) -> Result<Vec<Tile>, String> {
+    let mut remaining = rack.tiles.clone();
+    letters.chars()
+        .map(|letter| {
+            let is_blank = letter.is_lowercase();
+            let upper_letter = letter.to_uppercase().next().unwrap_or(letter);
+            let position = remaining.iter()
+                .position(|tile| if is_blank { tile.is_letterless() } else { tile.get_letter() == Some(letter) })
+                .ok_or_else(|| format!("The rack {} has no tile for {}.", rack, letter))?;
+            let tile = remaining.remove(position);
+            Ok(if is_blank { tile.fill_blank(upper_letter) } else { tile })
+        })
+        .collect()
+}
+
+/// Sort the candidates by how far the opponent's best reply to each would outscore it, smallest first.
+///
+/// The opponent holds the given rack. A play that goes out ends the game, so it has no reply. If the
+/// deadline passes, the candidates not yet searched keep their order after those that were.
+fn sort_by_reply(
+    dictionary: &Dawg, rejected_words: &HashSet<String>, parallel: bool, game_board: &GameBoard,
+    candidates: &mut [Candidate], opponent_rack: &[Tile], deadline: Option<Instant>
+) {
+    let opponent_rack = Rack{ tiles: opponent_rack.to_vec() };
+    let mut margins = Vec::new();
+    for candidate in candidates.iter() {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        let best_reply = if candidate.leave.is_empty() {
+            0
+        } else {
+            let reply_board = game_board.with_play(&candidate.played_tiles);
+            candidate_plays(dictionary, rejected_words, parallel, &reply_board, &opponent_rack, deadline).iter()
+                .map(|reply| reply.score)
+                .max()
+                .unwrap_or(0)
+        };
+        margins.push(best_reply - candidate.score);
+    }
+    let mut searched: Vec<(i32, Candidate)> = margins.into_iter().zip(candidates.iter().cloned()).collect();
+    searched.sort_by_key(|(margin, _candidate)| *margin);
+    for (index, (_margin, candidate)) in searched.into_iter().enumerate() {
+        candidates[index] = candidate;
+    }
+}
+
+/// Find every legal play of tiles from the rack, highest scoring first.
+///
+/// Unlike the controller, this uses no rejected words, time limit, or ranking by leave.
+pub fn find_plays(dictionary: &Dawg, game_board: &GameBoard, rack: &Rack) -> Vec<Candidate> {
+    let mut candidates = candidate_plays(dictionary, &HashSet::new(), true, game_board, rack, None);
+    candidates.sort_by_key(|candidate| cmp::Reverse(candidate.score));
+    candidates
+}
+
+/// Find every word that can be spelled with some of the rack tiles, regardless of any board.
+///
+/// Blanks may stand for any letter. The words are sorted by length and then alphabetically.
+pub fn rack_words(dictionary: &Dawg, rack: &Rack) -> Vec<String> {
+    let mut words = HashSet::new();
+    spell_words(dictionary, dictionary.root(), &mut String::new(), &mut rack.tiles.clone(), &mut words);
+    let mut words: Vec<String> = words.into_iter().collect();
+    words.sort_by_key(|word| (word.chars().count(), word.clone()));
+    words
+}
+
+/// Add each word that extends the partial word ending at the node using the remaining tiles.
+fn spell_words(dictionary: &Dawg, node: usize, word: &mut String, remaining: &mut Vec<Tile>, words: &mut HashSet<String>) {
+    if !word.is_empty() && dictionary.is_terminal(node) {
+        words.insert(word.clone());
+    }
+    // Duplicate tiles would spell the same words again.
+    for index in first_occurrences(remaining) {
+        let rack_tile = remaining.remove(index);
+        for (tile, child) in placements(dictionary, rack_tile, node) {
+            word.push(tile.get_letter().expect("A placed tile has a letter."));
+            spell_words(dictionary, child, word, remaining, words);
+            word.pop();
+        }
+        remaining.insert(index, rack_tile);
+    }
+}
+
+/// Get the ways of placing a rack tile after the partial word ending at the node, each with
+/// the node that the placement reaches.
+///
+/// A blank is assigned each letter that continues the partial word in the dictionary.
+fn placements(dictionary: &Dawg, rack_tile: Tile, node: usize) -> Vec<(Tile, usize)> {
+    match rack_tile.get_letter() {
+        Some(letter) => dictionary.child(node, letter)
+            .map(|child| (rack_tile, child))
+            .into_iter()
+            .collect(),
+        None => dictionary.children(node).iter()
+            .map(|(letter, child)| (rack_tile.fill_blank(*letter), *child))
+            .collect(),
+    }
+}
+
+impl AnchorSearch<'_> {
+    /// Generate all plays along an axis that cover the anchor and no earlier anchor.
+    ///
+    /// The tiles before the anchor are either existing board tiles or a left part built from the
+    /// rack on the empty, non-anchor squares before the anchor. The word is then extended over the
+    /// anchor and beyond, following the dictionary so that any partial word with no valid
+    /// extension is pruned.
+    fn generate_moves_at_anchor(&mut self, rack: &Rack, anchors: &[Coordinates]) {
+        let game_board = self.game_board;
+        let anchor = self.anchor;
+        let axis = self.axis;
+        let mut remaining = rack.tiles.clone();
+        if let Some(Some(_)) = game_board.get_tile(&anchor.offset(axis, -1)) {
+            let mut start = anchor.offset(axis, -1);
+            while let Some(Some(_)) = game_board.get_tile(&start.offset(axis, -1)) {
+                start = start.offset(axis, -1);
+            }
+            let mut node = Some(self.dictionary.root());
+            let mut position = start;
+            while position != anchor {
+                if let (Some(prefix_node), Some(Some(tile))) = (node, game_board.get_tile(&position)) {
+                    let letter = tile.get_letter().expect("A blank letter was found on the board.");
+                    node = self.dictionary.child(prefix_node, letter);
                 }
+                position = position.offset(axis, 1);
             }
-            return candidates;
+            if let Some(node) = node {
+                self.extend_right(node, anchor, &mut Vec::new(), &mut remaining);
+            }
+        } else {
+            let mut limit = 0;
+            let mut position = anchor.offset(axis, -1);
+            while let Some(None) = game_board.get_tile(&position) {
+                if anchors.contains(&position) {
+                    break;
+                }
+                limit += 1;
+                position = position.offset(axis, -1);
+            }
+            self.extend_left(&mut Vec::new(), self.dictionary.root(), &mut remaining, limit);
         }
-        let mut candidates: Vec<(Vec<PlayedTile>, i32)> = Vec::new();
-        for start_row in 0..game_board.get_rows() {
-            for start_column in 0..game_board.get_columns() {
-                let start_coordinates = Coordinates::new(start_row, start_column);
-                if game_board.is_occupied(&start_coordinates).unwrap_or(true) {
-                    continue;
+    }
+
+    /// Extend every left part by one more rack tile, up to the limit, and then extend each one
+    /// to the right from the anchor.
+    ///
+    /// The squares for the left part have no neighbouring board tiles, so there are no words
+    /// formed across the axis to check.
+    fn extend_left(&mut self, left_tiles: &mut Vec<Tile>, node: usize, remaining: &mut Vec<Tile>, limit: usize) {
+        if self.is_out_of_time() {
+            return;
+        }
+        let left_length = left_tiles.len() as i32;
+        let mut played_tiles: Vec<PlayedTile> = left_tiles.iter().enumerate()
+            .map(|(index, tile)| PlayedTile::new(self.anchor.offset(self.axis, index as i32 - left_length), *tile))
+            .collect();
+        self.extend_right(node, self.anchor, &mut played_tiles, remaining);
+        if limit == 0 {
+            return;
+        }
+        for index in first_occurrences(remaining) {
+            let rack_tile = remaining.remove(index);
+            for (tile, child) in placements(self.dictionary, rack_tile, node) {
+                left_tiles.push(tile);
+                self.extend_left(left_tiles, child, remaining, limit - 1);
+                left_tiles.pop();
+            }
+            remaining.insert(index, rack_tile);
+        }
+    }
+
+    /// Extend a partial word from the position, placing rack tiles on empty squares and reading
+    /// board tiles from occupied squares.
+    ///
+    /// The node is the dictionary node reached by the letters of the partial word.
+    fn extend_right(
+        &mut self, node: usize, position: Coordinates, played_tiles: &mut Vec<PlayedTile>, remaining: &mut Vec<Tile>
+    ) {
+        if self.is_out_of_time() {
+            return;
+        }
+        match self.game_board.get_tile(&position) {
+            None => self.record_play(node, played_tiles, remaining),
+            Some(None) => {
+                if position != self.anchor {
+                    self.record_play(node, played_tiles, remaining);
                 }
-                for axis in Axis::iterator() {
-                    for num_tiles in 1..rack.tiles.len() + 1 {
-                        // Check that it is ok to play this many tiles at this position.
-                        let feasibility_tiles: Vec<&Tile> = (0..num_tiles).map(|index| &rack.tiles[index]).collect();
-                        let played_tiles = game_board.build_played_tiles(&start_coordinates, feasibility_tiles, axis);
-                        if played_tiles.is_err() {
-                            continue;
-                        }
-                        let played_tiles = played_tiles.unwrap();
-                        if !game_board.is_connected(&played_tiles) && !game_board.is_through_center(&played_tiles) {
-                            continue;
-                        }
-                        let mut index_selection: Option<Vec<usize>> = Some((0..num_tiles).collect());
-                        while index_selection.is_some() {
-                            let mut ordering: Option<Vec<usize>> = Some((0..num_tiles).collect());
-                            while ordering.is_some() {
-                                let tiles_permutation: Vec<&Tile> = ordering.as_ref().unwrap()
-                                    .iter().map(|index| &rack.tiles[index_selection.as_ref().unwrap()[*index]])
-                                    .collect();
-
-                                let played_tiles = game_board.build_played_tiles(&start_coordinates, tiles_permutation, axis);
-                                let played_tiles = match played_tiles {
-                                    Ok(played_tiles) => played_tiles,
-                                    Err(e) => {
-                                        log::error!("Failed to build played tiles: {}", e);
-                                        ordering = next_permutation(ordering.unwrap());
-                                        continue;
-                                    },
-                                };
-                                let words_created = game_board.words_created(&played_tiles);
-                                if words_created.iter().all(|word| self.dictionary.contains(word)) {
-                                    let score = game_board.score(&played_tiles);
-                                    candidates.push((played_tiles, score));
-                                }
-                                ordering = next_permutation(ordering.unwrap());
-                            }
-                            index_selection = next_combination(index_selection.unwrap(), rack.tiles.len());
+                for index in first_occurrences(remaining) {
+                    let rack_tile = remaining.remove(index);
+                    for (tile, child) in placements(self.dictionary, rack_tile, node) {
+                        if self.cross_checks.allows(&position, tile.get_letter().unwrap()) {
+                            played_tiles.push(PlayedTile::new(position, tile));
+                            self.extend_right(child, position.offset(self.axis, 1), played_tiles, remaining);
+                            played_tiles.pop();
                         }
                     }
+                    remaining.insert(index, rack_tile);
                 }
-            }
+            },
+            Some(Some(board_tile)) => {
+                let letter = board_tile.get_letter().expect("A blank letter was found on the board.");
+                if let Some(child) = self.dictionary.child(node, letter) {
+                    self.extend_right(child, position.offset(self.axis, 1), played_tiles, remaining);
+                }
+            },
         }
-        candidates
     }
 
-    pub fn run(&mut self) {
-        let sleep_duration = Duration::from_secs(self.config.poll_interval_seconds as u64);
-        loop {
-            self.poll();
-            thread::sleep(sleep_duration);
+    fn is_out_of_time(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Add the play to the candidates if the node ends a complete word.
+    fn record_play(&mut self, node: usize, played_tiles: &[PlayedTile], remaining: &[Tile]) {
+        if played_tiles.is_empty() || !self.dictionary.is_terminal(node) {
+            return;
         }
+        let played_tiles = played_tiles.to_vec();
+        let (words_created, score) = match self.game_board.validate_play(&played_tiles, self.game_board.is_empty()) {
+            Ok(words_and_score) => words_and_score,
+            Err(_) => return,
+        };
+        if words_created.iter().any(|word| self.rejected_words.contains(word)) {
+            return;
+        }
+        let exposure = self.game_board.exposed_premium_value(&played_tiles);
+        self.candidates.push(Candidate{
+            played_tiles, score, leave: remaining.to_vec(), exposure, words: words_created
+        });
     }
 }
 
-fn load_dictionary() -> HashSet<String> {
-    let mut dictionary = HashSet::new();
-    let words_string = fs::read_to_string("dictionary.txt").expect("Error loading dictionary file.");
-    for word in words_string.lines() {
-        dictionary.insert(word.to_uppercase());
+/// Get the positions in the ranked candidates that a play is chosen from at the difficulty.
+///
+/// Hard always makes the best play, Medium chooses among the best quarter of the plays, and Easy
+/// chooses among the worse half.
+fn difficulty_choice_range(difficulty: Difficulty, candidate_count: usize) -> Range<usize> {
+    match difficulty {
+        Difficulty::Hard => 0..cmp::min(candidate_count, 1),
+        Difficulty::Medium => 0..candidate_count.div_ceil(4),
+        Difficulty::Easy => candidate_count / 2..candidate_count,
     }
-    dictionary
 }
 
+/// Rank every play for a game state saved as JSON, without contacting the server.
+///
+/// Return a description of each play, best first, with its rank.
+pub fn replay(config: &Config, game_path: &str) -> Result<Vec<String>, String> {
+    let game_string = fs::read_to_string(game_path)
+        .map_err(|e| format!("Failed to read game state {}: {}", game_path, e))?;
+    let game_state: GameSerializer = serde_json::from_str(&game_string)
+        .map_err(|e| format!("Failed to parse game state {}: {}", game_path, e))?;
+    let dictionary = load_dictionary(config.dictionary_path_for(game_state.dictionary.as_deref()))?;
+    let rejected_words = match &config.rejected_words_path {
+        Some(path) => load_rejected_words(path),
+        None => HashSet::new(),
+    };
+    let game_board = GameBoard::new(&game_state)
+        .map_err(|e| format!("Invalid board layout in {}: {}", game_path, e))?;
+    let rack = Rack::new(&game_state);
+    tracing::info!("Replaying game state {}:\n{}\nRack: {}", game_path, game_board, rack);
+    let mut candidates = candidate_plays(
+        &dictionary, &rejected_words, config.parallel_move_generation, &game_board, &rack, None
+    );
+    let weights = RankWeights::new(config);
+    Controller::sort_by_rank(&mut candidates, weights);
+    Ok(candidates.iter()
+        .map(|candidate| format!(
+            "{:.1}: {}", candidate.rank(weights), describe_play(candidate)
+        ))
+        .collect())
+}
+
+/// Describe a play with the words it forms, where each tile goes in board notation, and its score.
+///
+/// For example `CAT, AT with C at H8, A at I8, T at J8 for 10 points`. Blanks are shown in lower case,
+/// and squares in columns with no letter are shown as row and column.
+fn describe_play(candidate: &Candidate) -> String {
+    let placements: Vec<String> = candidate.played_tiles.iter()
+        .map(|played_tile| {
+            let coordinates = played_tile.get_coordinates_ref();
+            let square = coordinates.to_algebraic().unwrap_or_else(|_| coordinates.to_string());
+            format!("{} at {}", played_tile.get_tile_ref(), square)
+        })
+        .collect();
+    format!("{} with {} for {} points", candidate.words.join(", "), placements.join(", "), candidate.score)
+}
+
+/// Save the game state as JSON in the directory, creating the directory if needed.
+///
+/// The file is named for the game and turn. Return the path of the file.
+fn save_game_state(dir: &str, game_id: &str, game_state: &GameSerializer) -> Result<String, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory {}: {}", dir, e))?;
+    let path = Path::new(dir).join(format!("game_{}_turn_{}.json", game_id, game_state.turn_number));
+    let path = path.to_string_lossy().into_owned();
+    let game_string = serde_json::to_string_pretty(game_state)
+        .map_err(|e| format!("Failed to serialize game state: {}", e))?;
+    fs::write(&path, game_string).map_err(|e| format!("Failed to save game state {}: {}", path, e))?;
+    Ok(path)
+}
+
+fn load_dictionary(path: &str) -> Result<Dawg, String> {
+    let words_string = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to load dictionary {}: {}", path, e))?;
+    let dictionary = Dawg::new(words_string.lines().map(|word| word.to_uppercase()));
+    tracing::info!("Loaded dictionary with {} nodes.", dictionary.node_count());
+    Ok(dictionary)
+}
+
+/// Load the words previously rejected by the server, one per line.
+///
+/// A missing file is treated as an empty list since it is created on the first rejection.
+fn load_rejected_words(path: &str) -> HashSet<String> {
+    let mut rejected_words = HashSet::new();
+    match fs::read_to_string(path) {
+        Ok(words_string) => {
+            for word in words_string.lines() {
+                rejected_words.insert(word.trim().to_uppercase());
+            }
+        },
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::error!("Failed to load rejected words from {}: {}", path, e);
+            }
+        },
+    }
+    rejected_words
+}
+
+/// Describe the final scores of a completed game in turn order, and whether the AI player won.
+///
+/// As in the record, the AI player is identified by display name and the outcome is decided
+/// against the best opponent.
+fn describe_result(game: &GameInfo, ai_display_name: &str) -> String {
+    let mut game_players: Vec<&GamePlayerInfo> = game.game_players.iter().collect();
+    game_players.sort_by_key(|game_player| game_player.turn_order);
+    let scores: Vec<String> = game_players.iter()
+        .map(|game_player| format!("{} {}", game_player.player.display_name, game_player.score))
+        .collect();
+    let description = format!("Game {} finished: {}.", game.id, scores.join(", "));
+    let ai_score = match game_players.iter().find(|game_player| game_player.player.display_name == ai_display_name) {
+        Some(game_player) => game_player.score,
+        None => return description,
+    };
+    let best_opponent_score = game_players.iter()
+        .filter(|game_player| game_player.player.display_name != ai_display_name)
+        .map(|game_player| game_player.score)
+        .max()
+        .unwrap_or(0);
+    let outcome = match ai_score.cmp(&best_opponent_score) {
+        cmp::Ordering::Greater => format!("won by {}", ai_score - best_opponent_score),
+        cmp::Ordering::Less => format!("lost by {}", best_opponent_score - ai_score),
+        cmp::Ordering::Equal => String::from("tied"),
+    };
+    format!("{} {} {}.", description, ai_display_name, outcome)
+}
+
+/// Append a word to the rejected words file, creating the file if necessary.
+fn record_rejected_word(path: &str, word: &str) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use crate::slobsterble_client;
+    use crate::utilities::tests::TempPath;
+    use crate::models::game_models::{BoardError, GameBoardBuilder};
+    use crate::models::serializers::{
+        BoardLayoutSerializer, GamePlayerSerializer, ModifierSerializer, PlayedTileSerializer, PlayerInfo,
+        PlayerSerializer, PositionedModifierSerializer, TileCountSerializer, TileSerializer
+    };
+
+    impl Controller {
+        fn candidate_plays(&self, game_board: &GameBoard, rack: &Rack) -> Vec<Candidate> {
+            candidate_plays(
+                &self.dictionary, &self.rejected_words, self.config.parallel_move_generation, game_board, rack, None
+            )
+        }
+    }
+
+    fn test_controller(config: Config, words: &[&str]) -> Controller {
+        let dictionary = Arc::new(Dawg::new(words));
+        let rejected_words = Arc::new(HashSet::new());
+        let record = GameRecord::default();
+        let rng = StdRng::seed_from_u64(0);
+        Controller{
+            client: Arc::new(SlobsterbleClient::new(config.clone()).unwrap()), config, dictionary, dictionaries: HashMap::new(),
+            rejected_words, record,
+            rng, consecutive_passes: HashMap::new(), player_id: None, idle_turns: HashMap::new(),
+            active_game_count: 0, turns_since_heartbeat: 0, score_history: HashMap::new(),
+            consecutive_failures: HashMap::new(), failure_cooldowns: HashMap::new(), board_layouts: HashMap::new(),
+            reported_game_ids: HashSet::new(),
+        }
+    }
+
+    /// Build a controller whose client talks to the test server at the root url with fresh tokens.
+    fn served_controller(root_url: &str, extra_settings: &str, words: &[&str]) -> Controller {
+        let mut controller = test_controller(
+            Config::for_tests(&format!("{}[slobsterble]\nroot_url={}\n", extra_settings, root_url)), words
+        );
+        controller.client = Arc::new(slobsterble_client::tests::test_client(root_url, extra_settings));
+        controller
+    }
+
+    fn test_value(letter: char) -> i32 {
+        match letter {
+            'Q' | 'Z' => 10,
+            'K' => 5,
+            _ => 1,
+        }
+    }
+
+    fn test_game_state(rows: i32, columns: i32, rack_letters: &str) -> GameSerializer {
+        let rack = rack_letters.chars().map(|letter| TileCountSerializer{
+            tile: match letter {
+                '?' => TileSerializer{ letter: None, is_blank: true, value: 0 },
+                _ => TileSerializer{ letter: Some(String::from(letter)), is_blank: false, value: test_value(letter) },
+            },
+            count: 1,
+        }).collect();
+        GameSerializer{
+            board_state: Vec::new(),
+            game_players: Vec::new(),
+            board_layout: BoardLayoutSerializer{
+                rows, columns, modifiers: Vec::new(), bingo_bonus: 50, num_tiles: 7, start_square: None
+            },
+            turn_number: 0,
+            whose_turn_name: String::from("AI"),
+            num_tiles_remaining: 0,
+            rack,
+            prev_move: None,
+            fetcher_player_id: 0,
+            tile_distribution: Vec::new(),
+            dictionary: None,
+        }
+    }
+
+    fn candidate_words(controller: &Controller, game_board: &GameBoard, rack: &Rack) -> HashSet<String> {
+        controller.candidate_plays(game_board, rack).iter()
+            .flat_map(|candidate| game_board.words_created(&candidate.played_tiles).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rejected_play_is_skipped() {
+        let path = TempPath::new("rejected.txt");
+        let config = Config::for_tests(&format!("rejected_words_path={}\n", path.as_str()));
+        let mut controller = test_controller(config, &["AB", "BA"]);
+        let game_state = test_game_state(3, 3, "AB");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let expected_words: HashSet<String> = ["AB", "BA"].iter().map(|word| String::from(*word)).collect();
+        assert_eq!(candidate_words(&controller, &game_board, &rack), expected_words);
+
+        let rejected_candidate = controller.candidate_plays(&game_board, &rack).into_iter()
+            .find(|candidate| game_board.words_created(&candidate.played_tiles).unwrap() == vec!["AB"])
+            .unwrap();
+        controller.handle_rejected_play(None, &game_board, &rejected_candidate.played_tiles, "Bad request");
+        let expected_words: HashSet<String> = ["BA"].iter().map(|word| String::from(*word)).collect();
+        assert_eq!(candidate_words(&controller, &game_board, &rack), expected_words);
+        // The rejection survives a restart.
+        assert!(load_rejected_words(path.as_str()).contains("AB"));
+    }
+
+    #[test]
+    fn test_rejected_words_named_by_server() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let mut game_state = test_game_state(5, 5, "ZA");
+        game_state.board_state = vec![PlayedTileSerializer{
+            tile: TileSerializer{ letter: Some(String::from("A")), is_blank: false, value: 1 }, row: 1, column: 2
+        }];
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let played_tiles = [
+            PlayedTile::new(Coordinates::new(2, 1), rack.tiles[0]),
+            PlayedTile::new(Coordinates::new(2, 2), rack.tiles[1]),
+        ];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["ZA", "AA"]);
+        // The server does not name a word, and the play formed more than one.
+        controller.handle_rejected_play(None, &game_board, &played_tiles, "Bad request");
+        assert!(controller.rejected_words.is_empty());
+        // Only the word named in the reason is rejected.
+        controller.handle_rejected_play(Some("collins"), &game_board, &played_tiles, "Invalid words: za.");
+        assert_eq!(*controller.rejected_words, HashSet::from([String::from("ZA")]));
+    }
+
+    #[test]
+    fn test_single_tile_opening_is_not_generated() {
+        let controller = test_controller(Config::for_tests(""), &["A", "AT"]);
+        let game_state = test_game_state(5, 5, "AT");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let candidates = controller.candidate_plays(&game_board, &rack);
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|candidate| candidate.played_tiles.len() == 2));
+    }
+
+    #[test]
+    fn test_candidate_plays_through_anchors() {
+        let controller = test_controller(Config::for_tests(""), &["CAT", "CATS", "AT", "TA", "ST", "SCAT", "AS"]);
+        let mut game_state = test_game_state(5, 5, "AST");
+        game_state.board_state = "CAT".chars().enumerate().map(|(index, letter)| PlayedTileSerializer{
+            tile: TileSerializer{ letter: Some(String::from(letter)), is_blank: false, value: 1 },
+            row: 2,
+            column: index as i32 + 1,
+        }).collect();
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let candidates = controller.candidate_plays(&game_board, &rack);
+        let words = candidate_words(&controller, &game_board, &rack);
+        let expected_words: HashSet<String> = ["CAT", "CATS", "SCAT", "AT", "TA", "AS", "ST"].iter()
+            .map(|word| String::from(*word))
+            .collect();
+        assert_eq!(words, expected_words);
+        // Every play touches the existing tiles and forms only dictionary words.
+        for Candidate{ played_tiles, .. } in candidates.iter() {
+            assert!(played_tiles.iter().any(|played_tile| game_board.anchors().contains(played_tile.get_coordinates_ref())));
+            assert!(game_board.words_created(played_tiles).unwrap().iter().all(|word| controller.dictionary.contains(word)));
+        }
+        let cats_play = candidates.iter()
+            .find(|candidate| game_board.words_created(&candidate.played_tiles).unwrap() == vec!["CATS"])
+            .unwrap();
+        let s_tile = *rack.tiles.iter().find(|tile| tile.get_letter() == Some('S')).unwrap();
+        assert_eq!(cats_play.played_tiles, vec![PlayedTile::new(Coordinates::new(2, 4), s_tile)]);
+    }
+
+    #[test]
+    fn test_parallel_move_generation_matches_single_threaded() {
+        let words = ["CAT", "CATS", "AT", "TA", "ST", "SCAT", "AS", "TAS"];
+        let parallel_controller = test_controller(Config::for_tests(""), &words);
+        let serial_controller = test_controller(Config::for_tests("parallel_move_generation=false\n"), &words);
+        let game_state = test_game_state(7, 7, "CATS");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let candidates = parallel_controller.candidate_plays(&game_board, &rack);
+        assert!(!candidates.is_empty());
+        assert_eq!(candidates, serial_controller.candidate_plays(&game_board, &rack));
+    }
+
+    #[test]
+    fn test_board_layout_is_validated() {
+        let modifier = |row: i32, column: i32| PositionedModifierSerializer{
+            row, column, modifier: ModifierSerializer{ word_multiplier: 2, letter_multiplier: 1 }, is_start: false
+        };
+        let mut game_state = test_game_state(15, 15, "");
+        game_state.board_layout.modifiers = vec![modifier(0, 0), modifier(14, 14)];
+        assert!(GameBoard::new(&game_state).is_ok());
+        game_state.board_layout.modifiers.push(modifier(15, 3));
+        assert_eq!(GameBoard::new(&game_state).err(), Some(BoardError::RowOutOfBounds { row: 15, rows: 15 }));
+        game_state.board_layout.modifiers = vec![modifier(3, -1)];
+        assert_eq!(GameBoard::new(&game_state).err(), Some(BoardError::ColumnOutOfBounds { column: -1, columns: 15 }));
+        let err = GameBoard::new(&test_game_state(0, 15, "")).err().unwrap();
+        assert_eq!(err, BoardError::InvalidDimensions { rows: 0, columns: 15 });
+        assert_eq!(err.to_string(), "Invalid board dimensions of 0 rows and 15 columns.");
+        assert!(GameBoard::new(&test_game_state(15, -2, "")).is_err());
+        let mut game_state = test_game_state(15, 15, "");
+        game_state.board_state = vec![PlayedTileSerializer{
+            tile: TileSerializer{ letter: None, is_blank: true, value: 0 }, row: 7, column: 7
+        }];
+        assert_eq!(
+            GameBoard::new(&game_state).err(),
+            Some(BoardError::MissingLetter { coordinates: Coordinates::new(7, 7) })
+        );
+    }
+
+    #[test]
+    fn test_rack_words() {
+        let dictionary = Dawg::new(["A", "AT", "CAT", "CATS", "TAT", "TACT", "ACT", "SCAT", "ZA"]);
+        let rack = Rack::new(&test_game_state(15, 15, "TAC"));
+        assert_eq!(rack_words(&dictionary, &rack), vec!["A", "AT", "ACT", "CAT"]);
+        // The blank stands in for the second T or for the S.
+        let rack = Rack::new(&test_game_state(15, 15, "TAC?"));
+        assert_eq!(
+            rack_words(&dictionary, &rack),
+            vec!["A", "AT", "ZA", "ACT", "CAT", "TAT", "CATS", "SCAT", "TACT"]
+        );
+        assert!(rack_words(&dictionary, &Rack::new(&test_game_state(15, 15, ""))).is_empty());
+    }
+
+    #[test]
+    fn test_find_plays_are_highest_scoring_first() {
+        let dictionary = Dawg::new(["CAT", "AT", "TA"]);
+        let game_state = test_game_state(15, 15, "CAT");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let plays = find_plays(&dictionary, &game_board, &rack);
+        assert!(plays.iter().any(|play| play.get_played_tiles_ref().len() == 3));
+        assert!(plays.windows(2).all(|pair| pair[0].get_score() >= pair[1].get_score()));
+        for play in &plays {
+            assert_eq!(game_board.score(play.get_played_tiles_ref()), play.get_score());
+            assert_eq!(play.get_words_ref(), &game_board.words_created(play.get_played_tiles_ref()).unwrap());
+            // The leave is the rack tiles that the play does not use.
+            let mut rack_tiles = play.get_leave_ref().clone();
+            rack_tiles.extend(play.get_played_tiles_ref().iter().map(|played_tile| *played_tile.get_tile_ref()));
+            rack_tiles.sort();
+            let mut expected_tiles = rack.tiles.clone();
+            expected_tiles.sort();
+            assert_eq!(rack_tiles, expected_tiles);
+        }
+    }
+
+    #[test]
+    fn test_search_stops_at_deadline() {
+        let controller = test_controller(Config::for_tests(""), &["CAT", "AT", "TA"]);
+        let game_state = test_game_state(15, 15, "CAT");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let search = |deadline: Option<Instant>| candidate_plays(
+            &controller.dictionary, &controller.rejected_words, false, &game_board, &rack, deadline
+        );
+        let candidates = search(None);
+        assert!(!candidates.is_empty());
+        assert_eq!(search(Some(Instant::now() + Duration::from_secs(60))), candidates);
+        assert!(search(Some(Instant::now())).is_empty());
+    }
+
+    #[test]
+    fn test_blanks_are_assigned_any_letter() {
+        let controller = test_controller(Config::for_tests(""), &["ZAX", "QI"]);
+        let game_state = test_game_state(5, 5, "???I");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let candidates = controller.candidate_plays(&game_board, &rack);
+        let expected_words: HashSet<String> = ["ZAX", "QI"].iter().map(|word| String::from(*word)).collect();
+        assert_eq!(candidate_words(&controller, &game_board, &rack), expected_words);
+        // The I is played from the rack rather than a blank wherever possible.
+        let qi_plays: Vec<&Vec<PlayedTile>> = candidates.iter()
+            .map(|candidate| &candidate.played_tiles)
+            .filter(|played_tiles| game_board.words_created(played_tiles).unwrap() == vec!["QI"])
+            .collect();
+        assert!(qi_plays.iter().any(|played_tiles| !played_tiles[1].get_tile_ref().is_blank()));
+        assert!(candidates.iter().all(|candidate| {
+            candidate.played_tiles.iter().all(|played_tile| played_tile.get_tile_ref().get_letter().is_some())
+        }));
+    }
+
+    #[test]
+    fn test_leave_weight_ranking() {
+        // KATS scores one more than KAT, but keeping the S is worth more than that.
+        let words = ["KAT", "KATS", "TA"];
+        let game_state = test_game_state(5, 5, "KATS");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let best_word = |leave_weight: f64| {
+            let controller = test_controller(Config::for_tests(""), &words);
+            let mut candidates = controller.candidate_plays(&game_board, &rack);
+            Controller::sort_by_rank(&mut candidates, RankWeights{ leave: leave_weight, defense: 0.0 });
+            game_board.words_created(&candidates[0].played_tiles).unwrap()
+        };
+        assert_eq!(best_word(0.0), vec!["KATS"]);
+        assert_eq!(best_word(1.0), vec!["KAT"]);
+    }
+
+    fn chosen_opening(seed: u64) -> Vec<PlayedTile> {
+        let controller = test_controller(Config::for_tests(""), &["AB", "BA"]);
+        let game_state = test_game_state(5, 5, "AB");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let mut candidates = controller.candidate_plays(&game_board, &rack);
+        let weights = RankWeights{ leave: 1.0, defense: 0.0 };
+        Controller::sort_by_rank(&mut candidates, weights);
+        Controller::choose_tied_best(&mut candidates, weights, &mut StdRng::seed_from_u64(seed));
+        candidates.swap_remove(0).played_tiles
+    }
+
+    #[test]
+    fn test_balance_breaks_close_ranks() {
+        let tile = |letter: char| Rack::new(&test_game_state(1, 1, &letter.to_string())).tiles[0];
+        let candidate = |score: i32, leave: &str| Candidate{
+            played_tiles: Vec::new(), score, leave: leave.chars().map(tile).collect(), exposure: 0,
+            words: Vec::new(),
+        };
+        let weights = RankWeights{ leave: 0.0, defense: 0.0 };
+        let mut candidates = vec![candidate(12, "BCDF"), candidate(12, "AERST"), candidate(13, "BCDF")];
+        Controller::sort_by_rank(&mut candidates, weights);
+        let leaves: Vec<usize> = candidates.iter().map(|candidate| candidate.leave.len()).collect();
+        // A higher score still wins, and the balanced leave wins among equal scores.
+        assert_eq!(candidates[0].score, 13);
+        assert_eq!(leaves, vec![4, 5, 4]);
+    }
+
+    #[test]
+    fn test_defense_weight_ranking() {
+        let risky = Candidate{ played_tiles: Vec::new(), score: 20, leave: Vec::new(), exposure: 10, words: Vec::new() };
+        let defensive = Candidate{ played_tiles: Vec::new(), score: 18, leave: Vec::new(), exposure: 0, words: Vec::new() };
+        let mut candidates = vec![risky.clone(), defensive.clone()];
+        Controller::sort_by_rank(&mut candidates, RankWeights{ leave: 0.0, defense: 0.0 });
+        assert_eq!(candidates[0], risky);
+        Controller::sort_by_rank(&mut candidates, RankWeights{ leave: 0.0, defense: 0.5 });
+        assert_eq!(candidates[0], defensive);
+    }
+
+    #[test]
+    fn test_choose_tied_best_opening() {
+        // The same seed always chooses the same opening.
+        assert_eq!(chosen_opening(7), chosen_opening(7));
+        // Different seeds spread the choice across the tied openings.
+        let distinct_openings: BTreeSet<Vec<PlayedTile>> = (0..10).map(chosen_opening).collect();
+        assert!(distinct_openings.len() > 1);
+    }
+
+    #[test]
+    fn test_poll_stats() {
+        let mut stats = PollStats::default();
+        stats.record_turn("3", 120, Duration::from_millis(40), Duration::from_millis(2));
+        stats.record_turn("5", 900, Duration::from_millis(300), Duration::from_millis(9));
+        stats.record_turn("8", 0, Duration::from_millis(1), Duration::ZERO);
+        assert_eq!(
+            stats.to_string(),
+            "3 turns, 1020 candidates, 341 ms generating, 11 ms ranking. Largest search was 900 candidates in game 5."
+        );
+    }
+
+    #[test]
+    fn test_heartbeat_display() {
+        let heartbeat = Heartbeat{
+            active_games: 4, turns: 2, authenticated: true, uptime: Duration::from_secs(2 * 3600 + 5 * 60 + 9)
+        };
+        assert_eq!(
+            heartbeat.to_string(),
+            "4 active games, 2 turns since the last heartbeat, authenticated, up for 2h 5m 9s."
+        );
+        let heartbeat = Heartbeat{ active_games: 0, turns: 0, authenticated: false, uptime: Duration::from_secs(59) };
+        assert_eq!(
+            heartbeat.to_string(),
+            "0 active games, 0 turns since the last heartbeat, not authenticated, up for 0h 0m 59s."
+        );
+    }
+
+    #[test]
+    fn test_difficulty_choice() {
+        assert_eq!(difficulty_choice_range(Difficulty::Hard, 8), 0..1);
+        assert_eq!(difficulty_choice_range(Difficulty::Medium, 8), 0..2);
+        assert_eq!(difficulty_choice_range(Difficulty::Medium, 1), 0..1);
+        assert_eq!(difficulty_choice_range(Difficulty::Easy, 8), 4..8);
+        assert_eq!(difficulty_choice_range(Difficulty::Easy, 1), 0..1);
+        assert!(difficulty_choice_range(Difficulty::Hard, 0).is_empty());
+
+        let ranked_candidates: Vec<Candidate> = (0..8).rev()
+            .map(|score| Candidate{ played_tiles: Vec::new(), score, leave: Vec::new(), exposure: 0, words: Vec::new() })
+            .collect();
+        let chosen_score = |difficulty: Difficulty, seed: u64| {
+            let mut candidates = ranked_candidates.clone();
+            Controller::choose_for_difficulty(&mut candidates, difficulty, &mut StdRng::seed_from_u64(seed));
+            candidates[0].score
+        };
+        for seed in 0..10 {
+            assert_eq!(chosen_score(Difficulty::Hard, seed), 7);
+            assert!(chosen_score(Difficulty::Medium, seed) >= 6);
+            assert!(chosen_score(Difficulty::Easy, seed) <= 3);
+        }
+    }
+
+    fn test_game_player(id: i32, turn_order: i32, score: i32) -> GamePlayerSerializer {
+        let player = PlayerSerializer{ id, display_name: format!("Player {}", id) };
+        GamePlayerSerializer{ score, turn_order, player, num_tiles_remaining: 7 }
+    }
+
+    fn test_game_info(id: i32, whose_turn_name: &str, whose_turn_id: Option<i32>) -> GameInfo {
+        let game_player = |id: i32, display_name: &str| GamePlayerInfo{
+            score: 0, player: PlayerInfo{ id, display_name: String::from(display_name) }, turn_order: id
+        };
+        GameInfo{
+            started: chrono::Utc::now(),
+            completed: None,
+            whose_turn_name: String::from(whose_turn_name),
+            whose_turn_id,
+            turn_number: None,
+            game_players: vec![game_player(1, "AI"), game_player(2, "AI"), game_player(3, "Bob")],
+            id,
+        }
+    }
+
+    #[test]
+    fn test_report_completed_games() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let completed_game = |id: i32, scores: [i32; 2]| {
+            let mut game = test_game_info(id, "AI", None);
+            game.completed = Some(chrono::Utc::now());
+            game.game_players.remove(1);
+            for (game_player, score) in game.game_players.iter_mut().zip(scores) {
+                game_player.score = score;
+            }
+            game
+        };
+        let won = completed_game(4, [320, 280]);
+        let lost = completed_game(5, [250, 300]);
+        let tied = completed_game(6, [200, 200]);
+        assert_eq!(describe_result(&won, "AI"), "Game 4 finished: AI 320, Bob 280. AI won by 40.");
+        assert_eq!(describe_result(&lost, "AI"), "Game 5 finished: AI 250, Bob 300. AI lost by 50.");
+        assert_eq!(describe_result(&tied, "AI"), "Game 6 finished: AI 200, Bob 200. AI tied.");
+        assert_eq!(describe_result(&won, "Carol"), "Game 4 finished: AI 320, Bob 280.");
+
+        let games = vec![won, test_game_info(7, "AI", None)];
+        controller.report_completed_games(&games);
+        assert_eq!(controller.reported_game_ids, HashSet::from([4]));
+        let games = vec![completed_game(4, [320, 280]), lost];
+        controller.report_completed_games(&games);
+        assert_eq!(controller.reported_game_ids, HashSet::from([4, 5]));
+    }
+
+    #[test]
+    fn test_is_ai_turn() {
+        let mut game_state = test_game_state(5, 5, "");
+        game_state.fetcher_player_id = 2;
+        game_state.turn_number = 3;
+        assert!(!Controller::is_ai_turn("1", &game_state));
+        game_state.game_players = vec![test_game_player(1, 0, 0), test_game_player(2, 1, 0)];
+        assert!(Controller::is_ai_turn("1", &game_state));
+        game_state.turn_number = 4;
+        assert!(!Controller::is_ai_turn("1", &game_state));
+    }
+
+    #[test]
+    fn test_filter_ignored() {
+        let controller = test_controller(Config::for_tests("ignored_game_ids=11,13\n"), &[]);
+        let games = vec![
+            test_game_info(10, "AI", None), test_game_info(11, "AI", None), test_game_info(12, "AI", None)
+        ];
+        let game_ids: Vec<i32> = controller.filter_ignored(games).iter().map(|game| game.id).collect();
+        assert_eq!(game_ids, vec![10, 12]);
+    }
+
+    #[test]
+    fn test_filter_changed() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let game = |id: i32, turn_number: Option<i32>| GameInfo{ turn_number, ..test_game_info(id, "AI", None) };
+        let games = || vec![game(10, Some(4)), game(11, Some(7)), game(12, None), game(13, Some(2))];
+        let game_ids = |games: Vec<GameInfo>| games.iter().map(|game| game.id).collect::<Vec<i32>>();
+        assert_eq!(game_ids(controller.filter_changed(games())), vec![10, 11, 12, 13]);
+        controller.idle_turns.insert(String::from("10"), 4);
+        controller.idle_turns.insert(String::from("11"), 6);
+        controller.idle_turns.insert(String::from("12"), 1);
+        // Game 10 is still on the same turn, while a move has been made in game 11.
+        assert_eq!(game_ids(controller.filter_changed(games())), vec![11, 12, 13]);
+    }
+
+    #[test]
+    fn test_filter_by_turn() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let games = || vec![
+            test_game_info(10, "AI", Some(1)),
+            test_game_info(11, "AI", Some(2)),
+            test_game_info(12, "Bob", Some(3)),
+            test_game_info(13, "AI", None),
+        ];
+        let game_ids = |games: Vec<GameInfo>| games.iter().map(|game| game.id).collect::<Vec<i32>>();
+        // Before the AI player's id is known, both players named AI look like the AI player.
+        assert_eq!(game_ids(controller.filter_by_turn(games())), vec![10, 11, 13]);
+        controller.player_id = Some(1);
+        assert_eq!(game_ids(controller.filter_by_turn(games())), vec![10, 13]);
+        // A server that ignores the request to filter the games still has its games filtered.
+        let controller = test_controller(Config::for_tests("[slobsterble]\nfilter_games_by_turn=true\n"), &[]);
+        assert_eq!(game_ids(controller.filter_by_turn(games())), vec![10, 11, 13]);
+    }
+
+    #[test]
+    fn test_should_close_out() {
+        let controller = test_controller(
+            Config::for_tests("close_out_margin=50\nclose_out_tiles_remaining=7\n"), &[]
+        );
+        let mut game_state = test_game_state(5, 5, "");
+        game_state.fetcher_player_id = 1;
+        game_state.game_players = vec![test_game_player(1, 0, 300), test_game_player(2, 1, 240)];
+        game_state.num_tiles_remaining = 5;
+        assert!(controller.should_close_out(&game_state));
+        // Too early in the game.
+        game_state.num_tiles_remaining = 20;
+        assert!(!controller.should_close_out(&game_state));
+        // Lead is too small.
+        game_state.num_tiles_remaining = 5;
+        game_state.game_players[1].score = 260;
+        assert!(!controller.should_close_out(&game_state));
+        // Disabled without a configured margin.
+        let controller = test_controller(Config::for_tests(""), &[]);
+        game_state.game_players[1].score = 100;
+        assert!(!controller.should_close_out(&game_state));
+    }
+
+    #[test]
+    fn test_should_resign() {
+        let controller = test_controller(Config::for_tests("resign_margin=150\n"), &[]);
+        let mut game_state = test_game_state(5, 5, "");
+        game_state.fetcher_player_id = 1;
+        game_state.game_players = vec![test_game_player(1, 0, 100), test_game_player(2, 1, 260)];
+        assert!(controller.should_resign(&game_state));
+        // Tiles are left to draw.
+        game_state.num_tiles_remaining = 3;
+        assert!(!controller.should_resign(&game_state));
+        let controller = test_controller(
+            Config::for_tests("resign_margin=150\nresign_tiles_remaining=5\n"), &[]
+        );
+        assert!(controller.should_resign(&game_state));
+        // Deficit is too small.
+        game_state.game_players[1].score = 240;
+        assert!(!controller.should_resign(&game_state));
+        // Disabled without a configured margin.
+        let controller = test_controller(Config::for_tests(""), &[]);
+        game_state.game_players[1].score = 400;
+        assert!(!controller.should_resign(&game_state));
+    }
+
+    #[test]
+    fn test_close_out_prefers_emptying_rack() {
+        let controller = test_controller(Config::for_tests(""), &["ZA", "CAB"]);
+        let game_state = test_game_state(5, 5, "ZABC");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let mut candidates = controller.candidate_plays(&game_board, &rack);
+        Controller::sort_by_rank(&mut candidates, RankWeights{ leave: 0.0, defense: 0.0 });
+        assert_eq!(game_board.words_created(&candidates[0].played_tiles).unwrap(), vec!["ZA"]);
+        Controller::sort_for_close_out(&mut candidates, &game_board);
+        assert_eq!(game_board.words_created(&candidates[0].played_tiles).unwrap(), vec!["CAB"]);
+    }
+
+    #[test]
+    fn test_opponent_rack_in_endgame() {
+        let mut game_state = test_game_state(5, 5, "ZABC");
+        game_state.game_players = vec![test_game_player(1, 0, 300), test_game_player(2, 1, 240)];
+        let tile_count = |letter: &str, count: i32| TileCountSerializer{
+            tile: TileSerializer{ letter: Some(String::from(letter)), is_blank: false, value: 1 },
+            count,
+        };
+        game_state.tile_distribution = vec![
+            tile_count("A", 1), tile_count("B", 1), tile_count("C", 1), tile_count("Z", 2), tile_count("Q", 1)
+        ];
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let opponent_rack = endgame::opponent_rack(&game_state, &game_board, &rack).unwrap();
+        let opponent_letters: Vec<Option<char>> = opponent_rack.iter().map(|tile| tile.get_letter()).collect();
+        assert_eq!(opponent_letters, vec![Some('Q'), Some('Z')]);
+        // Nothing is known about the opponent's rack while tiles remain in the bag.
+        game_state.num_tiles_remaining = 1;
+        assert!(endgame::opponent_rack(&game_state, &game_board, &rack).is_none());
+    }
+
+    #[test]
+    fn test_endgame_prefers_going_out() {
+        let tile = |letter: char| Rack::new(&test_game_state(1, 1, &letter.to_string())).tiles[0];
+        let candidate = |score: i32, leave: &str| Candidate{
+            played_tiles: Vec::new(), score, leave: leave.chars().map(tile).collect(), exposure: 0,
+            words: Vec::new(),
+        };
+        let mut candidates = vec![candidate(30, "Z"), candidate(25, "E"), candidate(12, "")];
+        Controller::sort_for_endgame(&mut candidates, &[tile('Q')]);
+        let scores: Vec<i32> = candidates.iter().map(|candidate| candidate.score).collect();
+        // Going out wins the Q. Keeping the Z risks losing it if the opponent goes out.
+        assert_eq!(scores, vec![12, 25, 30]);
+        assert_eq!(endgame::spread(12, &[], &[tile('Q')]), 32);
+        assert_eq!(endgame::spread(30, &[tile('Z')], &[tile('Q')]), 10);
+    }
+
+    #[test]
+    fn test_unicode_words() {
+        let controller = test_controller(Config::for_tests(""), &["ÉTÉ", "ÇA", "ÀÇA"]);
+        let game_state = test_game_state(5, 5, "ÉTA?");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        // The blank can be the second É or the Ç, but not both letters that ÀÇA needs.
+        let expected_words = HashSet::from([String::from("ÉTÉ"), String::from("ÇA")]);
+        assert_eq!(candidate_words(&controller, &game_board, &rack), expected_words);
+        assert_eq!(rack_words(&controller.dictionary, &rack), vec!["ÇA", "ÉTÉ"]);
+    }
 
+    #[test]
+    fn test_sort_by_reply() {
+        let dictionary = Dawg::new(["AT", "TA", "ZA", "AZ", "ZAT"]);
+        let game_board = GameBoardBuilder::new(5, 5).tile(2, 2, 'A', 1).build();
+        let tile = |letter: char| Rack::new(&test_game_state(1, 1, &letter.to_string())).tiles[0];
+        let candidate = |column: i32, leave: &str| Candidate{
+            played_tiles: vec![PlayedTile::new(Coordinates::new(2, column), tile('T'))],
+            score: 2,
+            leave: leave.chars().map(tile).collect(),
+            exposure: 0,
+            words: Vec::new(),
+        };
+        // AT lets the opponent play ZAT for 12, while after TA the best reply is ZA for 11.
+        let mut candidates = vec![candidate(3, "E"), candidate(1, "E")];
+        sort_by_reply(&dictionary, &HashSet::new(), false, &game_board, &mut candidates, &[tile('Z')], None);
+        assert_eq!(candidates[0], candidate(1, "E"));
+        // Going out leaves the opponent no reply.
+        let mut candidates = vec![candidate(1, "E"), candidate(3, "")];
+        sort_by_reply(&dictionary, &HashSet::new(), false, &game_board, &mut candidates, &[tile('Z')], None);
+        assert_eq!(candidates[0], candidate(3, ""));
+        // Out of time, so nothing is reordered.
+        let mut candidates = vec![candidate(3, "E"), candidate(1, "E")];
+        let deadline = Some(Instant::now());
+        sort_by_reply(&dictionary, &HashSet::new(), false, &game_board, &mut candidates, &[tile('Z')], deadline);
+        assert_eq!(candidates[0], candidate(3, "E"));
+    }
+
+    #[test]
+    fn test_tiles_from_rack() {
+        let rack = Rack::new(&test_game_state(15, 15, "CAT?"));
+        let tiles = tiles_from_rack(&rack, "CsAT").unwrap();
+        let letters: Vec<Option<char>> = tiles.iter().map(|tile| tile.get_letter()).collect();
+        assert_eq!(letters, vec![Some('C'), Some('S'), Some('A'), Some('T')]);
+        assert!(tiles[1].is_blank());
+        assert!(!tiles[0].is_blank());
+        assert!(tiles_from_rack(&rack, "CC").is_err());
+        assert!(tiles_from_rack(&rack, "sa").is_err());
+
+        let serialized = serialize_play(&[PlayedTile::new(Coordinates::new(7, 8), tiles[1])]);
+        assert_eq!(serialized.len(), 1);
+        assert_eq!((serialized[0].row, serialized[0].column), (7, 8));
+        assert_eq!(serialized[0].letter, Some('S'));
+        assert!(serialized[0].is_blank && !serialized[0].is_exchange);
+    }
+
+    #[test]
+    fn test_exchange_selection() {
+        let rack = Rack::new(&test_game_state(5, 5, "EK?AQT"));
+        let selected_letters: Vec<Option<char>> = Controller::exchange_selection(&rack).iter()
+            .map(|tile| tile.get_letter())
+            .collect();
+        assert_eq!(selected_letters, vec![Some('Q')]);
+
+        let rack = Rack::new(&test_game_state(5, 5, "VVWIIUS"));
+        let selected_letters: Vec<Option<char>> = Controller::exchange_selection(&rack).iter()
+            .map(|tile| tile.get_letter())
+            .collect();
+        // The duplicates go while the S and a balanced mix of letters stay.
+        assert_eq!(selected_letters, vec![Some('V'), Some('I')]);
+
+        let blanks_rack = Rack::new(&test_game_state(5, 5, "??"));
+        assert_eq!(Controller::exchange_selection(&blanks_rack), blanks_rack.tiles[..1]);
+        assert!(Controller::exchange_selection(&Rack::new(&test_game_state(5, 5, ""))).is_empty());
+    }
+
+    #[test]
+    fn test_should_exchange() {
+        let controller = test_controller(Config::for_tests("exchange_margin=10\n"), &[]);
+        let mut game_state = test_game_state(5, 5, "IIIUUO?");
+        game_state.num_tiles_remaining = 50;
+        let rack = Rack::new(&game_state);
+        let tile = |letter: char| Rack::new(&test_game_state(1, 1, &letter.to_string())).tiles[0];
+        let candidate = |score: i32, leave: &str| Candidate{
+            played_tiles: Vec::new(), score, leave: leave.chars().map(tile).collect(), exposure: 0,
+            words: Vec::new(),
+        };
+        // Keeping the blank is worth more than a weak play that leaves a clogged rack.
+        assert!(controller.should_exchange(&game_state, &rack, &candidate(1, "IIIUU?")));
+        assert!(!controller.should_exchange(&game_state, &rack, &candidate(30, "IIIUU?")));
+        // There are not enough tiles left in the bag.
+        let mut late_game_state = test_game_state(5, 5, "IIIUUO?");
+        late_game_state.num_tiles_remaining = 3;
+        assert!(!controller.should_exchange(&late_game_state, &rack, &candidate(1, "IIIUU?")));
+        // Disabled without a configured margin.
+        let controller = test_controller(Config::for_tests(""), &[]);
+        assert!(!controller.should_exchange(&game_state, &rack, &candidate(1, "IIIUU?")));
+        // A minimum play score works without a margin.
+        let controller = test_controller(Config::for_tests("min_play_score=8\n"), &[]);
+        assert!(controller.should_exchange(&game_state, &rack, &candidate(5, "IIU")));
+        assert!(!controller.should_exchange(&game_state, &rack, &candidate(8, "IIU")));
+        assert!(!controller.should_exchange(&late_game_state, &rack, &candidate(5, "IIU")));
+    }
+
+    #[test]
+    fn test_jittered() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let duration = Duration::from_secs(10);
+        assert_eq!(jittered(duration, 0, &mut rng), duration);
+        let sleeps: Vec<Duration> = (0..100).map(|_| jittered(duration, 20, &mut rng)).collect();
+        assert!(sleeps.iter().all(|sleep| Duration::from_secs(8) <= *sleep && *sleep <= Duration::from_secs(12)));
+        assert!(sleeps.iter().any(|sleep| *sleep != sleeps[0]));
+    }
+
+    #[test]
+    fn test_board_layout_is_cached() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let game_id = String::from("7");
+        let layout = controller.board_layout(&game_id, &test_game_state(15, 15, "")).unwrap();
+        // The layout is not built again for later turns in the game.
+        let cached = controller.board_layout(&game_id, &test_game_state(0, 0, "")).unwrap();
+        assert!(Arc::ptr_eq(&layout, &cached));
+        assert!(controller.board_layout(&String::from("8"), &test_game_state(0, 0, "")).is_err());
+        let game_board = GameBoard::with_layout(cached, &test_game_state(0, 0, "CAT")).unwrap();
+        assert_eq!(game_board.anchors(), vec![Coordinates::new(7, 7)]);
+    }
+
+    #[test]
+    fn test_game_dictionary() {
+        let mut controller = test_controller(Config::for_tests(""), &["CAT"]);
+        let twl = Arc::new(Dawg::new(["DOG"]));
+        controller.dictionaries.insert(String::from("twl"), Arc::clone(&twl));
+        let mut game_state = test_game_state(5, 5, "");
+        assert!(Arc::ptr_eq(&controller.game_dictionary("7", &game_state), &controller.dictionary));
+        game_state.dictionary = Some(String::from("TWL"));
+        assert!(Arc::ptr_eq(&controller.game_dictionary("7", &game_state), &twl));
+        game_state.dictionary = Some(String::from("sowpods"));
+        assert!(Arc::ptr_eq(&controller.game_dictionary("7", &game_state), &controller.dictionary));
+    }
+
+    #[test]
+    fn test_failure_cooldown() {
+        let mut controller = test_controller(
+            Config::for_tests("failure_threshold=2\nfailure_cooldown_polls=2\n"), &[]
+        );
+        let game_id = String::from("7");
+        controller.record_failure(&game_id);
+        assert!(!controller.is_cooling_down(&game_id));
+        controller.record_failure(&game_id);
+        assert!(controller.is_cooling_down(&game_id));
+        assert!(controller.is_cooling_down(&game_id));
+        assert!(!controller.is_cooling_down(&game_id));
+        // Failing again after the cooldown skips the game again.
+        controller.record_failure(&game_id);
+        assert_eq!(controller.consecutive_failures.get(&game_id), Some(&3));
+        assert!(controller.is_cooling_down(&game_id));
+
+        let mut controller = test_controller(Config::for_tests("failure_threshold=2\n"), &[]);
+        controller.record_failure(&game_id);
+        controller.record_failure(&game_id);
+        assert!(!controller.is_cooling_down(&game_id));
+    }
+
+    #[tokio::test]
+    async fn test_cooled_down_game_is_played_again() {
+        const GAMES: &str = r#"[{"started": "1650000000", "completed": null, "whose_turn_name": "AI",
+            "whose_turn_id": 1, "turn_number": 0, "game_players": [], "id": 7}]"#;
+        const GAME: &str = r#"{"board_state": [], "game_players": [{"score": 0, "turn_order": 0,
+            "player": {"id": 1, "display_name": "AI"}, "num_tiles_remaining": 3}],
+            "board_layout": {"rows": 5, "columns": 5, "modifiers": []},
+            "turn_number": 0, "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [
+                {"tile": {"letter": "C", "is_blank": false, "value": 3}, "count": 1},
+                {"tile": {"letter": "A", "is_blank": false, "value": 1}, "count": 1},
+                {"tile": {"letter": "T", "is_blank": false, "value": 1}, "count": 1}
+            ], "prev_move": null, "fetcher_player_id": 1}"#;
+        let (root_url, handle) = slobsterble_client::tests::serve_responses(vec![
+            (200, GAMES), (200, GAME), (200, GAMES), (200, GAME), (200, GAMES), (200, GAME),
+        ]);
+        let mut controller = served_controller(&root_url, "dry_run=true\nfailure_cooldown_polls=2\n", &["CAT"]);
+        controller.failure_cooldowns.insert(String::from("7"), 2);
+        controller.poll().await;
+        controller.poll().await;
+        assert_eq!(controller.turns_since_heartbeat, 0);
+        // The cooldown is over, so the unchanged turn is played.
+        controller.poll().await;
+        assert_eq!(controller.turns_since_heartbeat, 1);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_is_dead_game() {
+        let mut controller = test_controller(Config::for_tests(""), &[]);
+        let game_id = String::from("3");
+        let mut game_state = test_game_state(5, 5, "");
+        game_state.prev_move = Some(PrevMoveSerializer{
+            word: None, score: 0, player_id: 2, display_name: String::from("Player 2"), exchanged_count: 0,
+        });
+        assert!(!controller.is_dead_game(&game_id, &game_state));
+        controller.consecutive_passes.insert(game_id.clone(), 1);
+        assert!(!controller.is_dead_game(&game_id, &game_state));
+        controller.consecutive_passes.insert(game_id.clone(), 2);
+        assert!(controller.is_dead_game(&game_id, &game_state));
+        // The opponent made a play so the game is still alive.
+        game_state.prev_move.as_mut().unwrap().word = Some(String::from("CAT"));
+        assert!(!controller.is_dead_game(&game_id, &game_state));
+    }
+
+    #[tokio::test]
+    async fn test_self_test() {
+        let (root_url, handle) = slobsterble_client::tests::serve_statuses(vec![200]);
+        let controller = served_controller(&root_url, "", &["CAT"]);
+        assert_eq!(controller.self_test().await, Ok(String::from("Ready to play as AI with 0 games listed.")));
+        handle.join().unwrap();
+
+        // The server rejects the login, though it still lists the games.
+        let (root_url, handle) = slobsterble_client::tests::serve_statuses(vec![401, 200]);
+        let controller = test_controller(Config::for_tests(&format!("[slobsterble]\nroot_url={}\n", root_url)), &["CAT"]);
+        assert_eq!(controller.self_test().await, Err(String::from("Failed to log in as ai.")));
+        handle.join().unwrap();
+
+        let (root_url, handle) = slobsterble_client::tests::serve_statuses(vec![200]);
+        let controller = served_controller(&root_url, "", &[]);
+        let err = controller.self_test().await.unwrap_err();
+        assert!(err.ends_with("has no words."), "{}", err);
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_long_polls_are_kept_across_rounds() {
+        // The first account's server answers each wait at once.
+        let (root_url, handle) = slobsterble_client::tests::serve_with_headers(vec![
+            (200, "X-Long-Poll: 1\r\n", "[]"), (200, "X-Long-Poll: 1\r\n", "[]"),
+        ]);
+        // The second account's server never accepts the connection, so its wait does not end.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let idle_url = format!("http://{}/", listener.local_addr().unwrap());
+        let controllers = [served_controller(&root_url, "", &[]), served_controller(&idle_url, "", &[])];
+        let mut long_polls = LongPolls::default();
+        for _ in 0..2 {
+            assert!(long_polls.wait_for_change(&controllers, 30).await.unwrap());
+            // The second account is still on its first wait.
+            assert_eq!(long_polls.waiting_accounts, HashSet::from([1]));
+            assert_eq!(long_polls.waits.len(), 1);
+        }
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_long_poll_renewing_tokens_does_not_block_polls() {
+        // The first account's server is slow to renew the access token, and lists no games.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let renewing_url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                io::BufRead::read_line(&mut reader, &mut request_line).unwrap();
+                let mut line = String::new();
+                while io::BufRead::read_line(&mut reader, &mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                let body = if request_line.contains("refresh-access") {
+                    std::thread::sleep(Duration::from_millis(300));
+                    String::from(r#"{"token": "renewed", "expiration_date": 4102444800}"#)
+                } else {
+                    String::from("[]")
+                };
+                let _ = write!(
+                    stream, "HTTP/1.1 200 OK\r\nX-Long-Poll: 1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+            }
+        });
+        // The second account's server answers its wait at once, so it wins the round.
+        let (root_url, handle) = slobsterble_client::tests::serve_with_headers(vec![(200, "X-Long-Poll: 1\r\n", "[]")]);
+        let mut renewing_controller = served_controller(&renewing_url, "", &[]);
+        renewing_controller.client = Arc::new(slobsterble_client::tests::expired_access_client(&renewing_url));
+        let controllers = [renewing_controller, served_controller(&root_url, "", &[])];
+        let mut long_polls = LongPolls::default();
+        assert!(long_polls.wait_for_change(&controllers, 30).await.unwrap());
+        assert_eq!(long_polls.waiting_accounts, HashSet::from([0]));
+        handle.join().unwrap();
+        // Listing the first account's games waits for the renewal to finish, not for the next round.
+        let games = tokio::time::timeout(Duration::from_secs(5), controllers[0].client.list_games()).await;
+        assert!(games.expect("Listing the games waited on the unfinished long poll.").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_play_is_retried_once_unless_made() {
+        const TURN_0: &str = r#"{"board_state": [], "game_players": [],
+            "board_layout": {"rows": 5, "columns": 5, "modifiers": []}, "turn_number": 0,
+            "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [], "prev_move": null, "fetcher_player_id": 1}"#;
+        const TURN_1: &str = r#"{"board_state": [], "game_players": [],
+            "board_layout": {"rows": 5, "columns": 5, "modifiers": []}, "turn_number": 1,
+            "whose_turn_name": "Bob", "num_tiles_remaining": 80, "rack": [], "prev_move": null, "fetcher_player_id": 1}"#;
+        let game_state = test_game_state(5, 5, "");
+        // The game has not moved on, so the play is made again.
+        let (root_url, handle) = slobsterble_client::tests::serve_responses(vec![(503, ""), (200, TURN_0), (200, "OK")]);
+        let controller = served_controller(&root_url, "", &[]);
+        assert_eq!(controller.play_candidate("3", &game_state, &Vec::new()).await.unwrap(), "OK");
+        handle.join().unwrap();
+
+        // The play was made before the error, so it is not made again.
+        let (root_url, handle) = slobsterble_client::tests::serve_responses(vec![(500, ""), (200, TURN_1)]);
+        let controller = served_controller(&root_url, "", &[]);
+        assert!(controller.play_candidate("3", &game_state, &Vec::new()).await.is_ok());
+        handle.join().unwrap();
+
+        // Only one more attempt is made, and errors that will happen again are not retried.
+        let (root_url, handle) = slobsterble_client::tests::serve_responses(vec![(503, ""), (200, TURN_0), (503, ""), (401, "")]);
+        let controller = served_controller(&root_url, "", &[]);
+        assert!(controller.play_candidate("3", &game_state, &Vec::new()).await.is_err());
+        assert!(controller.play_candidate("3", &game_state, &Vec::new()).await.is_err());
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_passes_reset_after_opponent_moves() {
+        let (root_url, handle) = slobsterble_client::tests::serve_statuses(vec![200, 200, 200]);
+        let mut controller = served_controller(&root_url, "request_max_attempts=1\n", &[]);
+        let game_id = String::from("3");
+        assert!(controller.pass_turn(&game_id, 0).await.is_ok());
+        assert!(controller.pass_turn(&game_id, 0).await.is_ok());
+        assert!(controller.pass_turn(&game_id, 0).await.is_err());
+        let mut game_state = test_game_state(5, 5, "");
+        game_state.prev_move = Some(PrevMoveSerializer{
+            word: None, score: 0, player_id: 2, display_name: String::from("Player 2"), exchanged_count: 0,
+        });
+        // The opponent passing too does not let the game make progress.
+        controller.reset_passes_after_move(&game_id, &game_state);
+        assert!(controller.pass_turn(&game_id, 0).await.is_err());
+        game_state.prev_move.as_mut().unwrap().word = Some(String::from("CAT"));
+        controller.reset_passes_after_move(&game_id, &game_state);
+        assert!(controller.pass_turn(&game_id, 0).await.is_ok());
+        handle.join().unwrap();
+        // Each pass is recorded without a word, even though the score is not checked.
+        assert_eq!(controller.score_history(&game_id).len(), 3);
+        assert!(controller.score_history(&game_id).iter().all(|turn| turn.word.is_none() && turn.score == 0));
+    }
+
+    #[test]
+    fn test_describe_play() {
+        let controller = test_controller(Config::for_tests(""), &["CAT"]);
+        let game_state = test_game_state(15, 15, "CAT");
+        let game_board = GameBoard::new(&game_state).unwrap();
+        let rack = Rack::new(&game_state);
+        let candidates = controller.candidate_plays(&game_board, &rack);
+        let candidate = candidates.iter()
+            .find(|candidate| candidate.played_tiles[0].get_coordinates_ref() == &Coordinates::new(7, 7)
+                && candidate.played_tiles[1].get_coordinates_ref() == &Coordinates::new(7, 8))
+            .unwrap();
+        assert_eq!(candidate.words, vec![String::from("CAT")]);
+        assert_eq!(
+            describe_play(candidate),
+            format!("CAT with C at H8, A at I8, T at J8 for {} points", candidate.score)
+        );
+    }
+
+    #[test]
+    fn test_replay_ranks_saved_game_state() {
+        let dictionary_path = TempPath::new("replay_dictionary.txt");
+        let game_path = TempPath::new("replay_game.json");
+        fs::write(&dictionary_path, "CAT\nAT\n").unwrap();
+        let tile_count = |letter: &str| format!(
+            r#"{{"tile": {{"letter": "{}", "is_blank": false, "value": 1}}, "count": 1}}"#, letter
+        );
+        fs::write(&game_path, format!(
+            r#"{{"board_state": [], "game_players": [], "board_layout": {{"rows": 15, "columns": 15, "modifiers": []}},
+            "turn_number": 0, "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [{}, {}, {}],
+            "prev_move": null, "fetcher_player_id": 1}}"#,
+            tile_count("C"), tile_count("A"), tile_count("T")
+        )).unwrap();
+        let config = Config::for_tests(&format!("dictionary_path={}\n", dictionary_path.as_str()));
+        let ranked_plays = replay(&config, game_path.as_str()).unwrap();
+        assert!(!ranked_plays.is_empty());
+        assert!(ranked_plays[0].contains("CAT with"));
+        assert!(ranked_plays.iter().any(|play| play.contains("AT with")));
+    }
+
+    #[test]
+    fn test_save_game_state() {
+        let dir = TempPath::new("score_mismatches");
+        let mut game_state = test_game_state(15, 15, "CAT");
+        game_state.turn_number = 4;
+        let path = save_game_state(dir.as_str(), "12", &game_state).unwrap();
+        assert!(path.ends_with("game_12_turn_4.json"));
+        let saved: GameSerializer = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.turn_number, 4);
+        assert_eq!(saved.rack.len(), 3);
+    }
+
+    #[test]
+    fn test_missing_dictionary_is_an_error() {
+        let path = TempPath::new("missing_dictionary.txt");
+        let config = Config::for_tests(&format!("dictionary_path={}\n", path.as_str()));
+        let failure_reason = Controller::for_accounts(&config).err().unwrap();
+        assert!(failure_reason.contains(path.as_str()));
+    }
+
+    #[test]
+    fn test_controller_for_each_account() {
+        let path = TempPath::new("account_dictionary.txt");
+        fs::write(&path, "CAT\n").unwrap();
+        let config = Config::for_tests(&format!(
+            "dictionary_path={}\n[account:first]\nusername=alice\npassword=a\ndisplay_name=Alice\n\
+            [account:second]\nusername=bob\npassword=b\ndisplay_name=Bob\n",
+            path.as_str()
+        ));
+        let controllers = Controller::for_accounts(&config).unwrap();
+        let display_names: Vec<&str> = controllers.iter()
+            .map(|controller| controller.config.ai_display_name.as_str())
+            .collect();
+        assert_eq!(display_names, vec!["Alice", "Bob"]);
+        assert!(Arc::ptr_eq(&controllers[0].dictionary, &controllers[1].dictionary));
+    }
+
+    #[test]
+    fn test_score_history() {
+        let path = TempPath::new("score_history.jsonl");
+        let mut controller = test_controller(Config::for_tests(&format!("score_history_path={}\n", path.as_str())), &["CAT"]);
+        controller.record_turn("7", 0, Some(String::from("CAT")), 10);
+        controller.record_turn("8", 1, Some(String::from("AT")), 4);
+        controller.record_turn("7", 2, None, 0);
+        let words: Vec<Option<&str>> = controller.score_history("7").iter().map(|turn| turn.word.as_deref()).collect();
+        assert_eq!(words, vec![Some("CAT"), None]);
+        assert_eq!(controller.score_history("8")[0].score, 4);
+        assert!(controller.score_history("9").is_empty());
+        let history = fs::read_to_string(&path).unwrap();
+        assert_eq!(history.lines().count(), 3);
+    }
+}
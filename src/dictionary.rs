@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// A node in a DAWG, identified by its index into the DAWG's nodes.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct DawgNode {
+    is_terminal: bool,
+    children: Vec<(char, usize)>,
+}
+
+/// A node in the uncompressed trie that a DAWG is built from.
+#[derive(Default)]
+struct TrieNode {
+    is_terminal: bool,
+    children: BTreeMap<char, TrieNode>,
+}
+
+/// A directed acyclic word graph.
+///
+/// This is a trie in which identical suffix subtrees are shared, so that the words can be walked
+/// one letter at a time while move generation prunes any partial word with no valid extension.
+pub struct Dawg {
+    nodes: Vec<DawgNode>,
+    root: usize,
+}
+
+impl Dawg {
+    pub fn new<I, S>(words: I) -> Dawg
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut trie = TrieNode::default();
+        for word in words {
+            let mut node = &mut trie;
+            for letter in word.as_ref().chars() {
+                node = node.children.entry(letter).or_default();
+            }
+            node.is_terminal = true;
+        }
+        let mut nodes = Vec::new();
+        let mut register = HashMap::new();
+        let root = Dawg::minimize(&trie, &mut nodes, &mut register);
+        Dawg{ nodes, root }
+    }
+
+    /// Add the trie node to the DAWG nodes, reusing an existing node with identical children.
+    ///
+    /// Return the index of the DAWG node.
+    fn minimize(
+        trie_node: &TrieNode, nodes: &mut Vec<DawgNode>, register: &mut HashMap<DawgNode, usize>
+    ) -> usize {
+        let children = trie_node.children.iter()
+            .map(|(letter, child)| (*letter, Dawg::minimize(child, nodes, register)))
+            .collect();
+        let node = DawgNode{ is_terminal: trie_node.is_terminal, children };
+        if let Some(index) = register.get(&node) {
+            return *index;
+        }
+        nodes.push(node.clone());
+        register.insert(node, nodes.len() - 1);
+        nodes.len() - 1
+    }
+
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Get the node reached by following the letter from the node, if any.
+    pub fn child(&self, node: usize, letter: char) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        children.binary_search_by_key(&letter, |(child_letter, _child)| *child_letter).ok()
+            .map(|index| children[index].1)
+    }
+
+    /// Get the letters leading out of the node, in alphabetical order, with the nodes they reach.
+    pub fn children(&self, node: usize) -> &[(char, usize)] {
+        &self.nodes[node].children
+    }
+
+    /// Return true iff the letters on the path from the root to the node form a word.
+    pub fn is_terminal(&self, node: usize) -> bool {
+        self.nodes[node].is_terminal
+    }
+
+    /// Get the node reached by following the letters of the word from the root, if any.
+    pub fn walk(&self, word: &str) -> Option<usize> {
+        word.chars().try_fold(self.root, |node, letter| self.child(node, letter))
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.walk(word).is_some_and(|node| self.is_terminal(node))
+    }
+
+    /// Return true iff some word starts with the prefix. Every word is a prefix of itself.
+    pub fn has_prefix(&self, prefix: &str) -> bool {
+        self.walk(prefix).is_some()
+    }
+
+    /// Return true iff the DAWG holds no words.
+    pub fn is_empty(&self) -> bool {
+        self.children(self.root).is_empty() && !self.is_terminal(self.root)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dawg_queries() {
+        let dawg = Dawg::new(["CAT", "CATS", "CAR", "CARS", "DOG"]);
+        assert!(dawg.contains("CAT"));
+        assert!(dawg.contains("CARS"));
+        assert!(!dawg.contains("CA"));
+        assert!(!dawg.contains("DOGS"));
+        assert!(dawg.has_prefix(""));
+        assert!(dawg.has_prefix("CA"));
+        assert!(dawg.has_prefix("CATS"));
+        assert!(dawg.has_prefix("DO"));
+        assert!(!dawg.has_prefix("CATZ"));
+        assert!(!dawg.has_prefix("X"));
+        assert!(!dawg.is_empty());
+        assert!(Dawg::new(Vec::<&str>::new()).is_empty());
+        assert!(!Dawg::new([""]).is_empty());
+
+        let node = dawg.walk("CA").unwrap();
+        let letters: Vec<char> = dawg.children(node).iter().map(|(letter, _child)| *letter).collect();
+        assert_eq!(letters, vec!['R', 'T']);
+        let node = dawg.child(node, 'T').unwrap();
+        assert!(dawg.is_terminal(node));
+        assert_eq!(dawg.child(node, 'Z'), None);
+    }
+
+    #[test]
+    fn test_dawg_shares_suffixes() {
+        // CAT/CATS and CAR/CARS end in the same subtrees, as do the final letters of every word.
+        let dawg = Dawg::new(["CAT", "CATS", "CAR", "CARS", "DOG"]);
+        let cat = dawg.walk("CAT").unwrap();
+        let car = dawg.walk("CAR").unwrap();
+        assert_eq!(cat, car);
+        assert_eq!(dawg.walk("CATS").unwrap(), dawg.walk("DOG").unwrap());
+        // root, C, CA, D, DO, CAT/CAR and the shared end node.
+        assert_eq!(dawg.node_count(), 7);
+    }
+}
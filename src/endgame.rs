@@ -0,0 +1,34 @@
+use crate::models::game_models::{GameBoard, Rack, Tile, TileBag};
+use crate::models::serializers::GameSerializer;
+
+/// Get the opponent's rack once the bag is empty in a two player game.
+///
+/// The opponent then holds exactly the tiles that are on neither the board nor the rack.
+/// Return None if tiles remain in the bag, there is more than one opponent, or the game did
+/// not give a tile distribution.
+pub fn opponent_rack(game_state: &GameSerializer, game_board: &GameBoard, rack: &Rack) -> Option<Vec<Tile>> {
+    if game_state.num_tiles_remaining > 0 || game_state.game_players.len() != 2 {
+        return None;
+    }
+    let tile_bag = TileBag::new(game_state);
+    if tile_bag.is_empty() {
+        return None;
+    }
+    Some(tile_bag.unseen_tiles(game_board, rack))
+}
+
+/// Estimate the difference a play makes to the final margin against the opponent.
+///
+/// Going out wins the value of the opponent's rack, which the opponent also loses. Otherwise
+/// the opponent may go out on their next turn and win the value of the tiles left on the rack.
+pub fn spread(score: i32, leave: &[Tile], opponent_rack: &[Tile]) -> i32 {
+    if leave.is_empty() {
+        score + 2 * rack_value(opponent_rack)
+    } else {
+        score - 2 * rack_value(leave)
+    }
+}
+
+fn rack_value(tiles: &[Tile]) -> i32 {
+    tiles.iter().map(|tile| tile.get_value()).sum()
+}
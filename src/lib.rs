@@ -0,0 +1,6 @@
+pub mod slobsterble_client;
+pub mod controller;
+pub mod dictionary;
+pub mod endgame;
+pub mod models;
+mod utilities;
@@ -1,40 +1,157 @@
-mod slobsterble_client;
-mod controller;
-mod models;
-mod utilities;
-
-use log;
 use std::env;
 use std::path::PathBuf;
 use std::process;
 
+use aislobsterble::controller;
+use aislobsterble::controller::Controller;
+use aislobsterble::models;
+use aislobsterble::models::config_models::LogFormat;
+use aislobsterble::models::game_models::{Axis, Coordinates, MAX_ALGEBRAIC_COLUMNS};
 use configparser::ini::Ini;
-use crate::controller::Controller;
+use tracing_subscriber::EnvFilter;
+
+const CONFIG_ENV_VAR: &str = "AISLOBSTERBLE_CONFIG";
 
 
-fn main() {
+#[tokio::main]
+async fn main() {
     // Parse configuration.
     let mut config_ini = Ini::new();
 
-    let config_path = get_config_path();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--version") {
+        println!("{}", version(option_env!("AISLOBSTERBLE_GIT_HASH")));
+        return;
+    }
+    let config_path = match config_path_override(&args, env::var(CONFIG_ENV_VAR).ok()) {
+        Some(config_path) => config_path,
+        None => get_config_path(),
+    };
     if let Err(failure_reason) = config_ini.load(config_path.clone()) {
-        env::set_var("RUST_LOG", "aislobsterble=info");
-        env_logger::init();
-        log::info!("Loading config from {:?}", &config_path);
-        log::error!("Failed to load config: {}", failure_reason);
+        init_logging("info", LogFormat::Text);
+        tracing::info!("Loading config from {:?}", &config_path);
+        tracing::error!("Failed to load config: {}", failure_reason);
         process::exit(1);
     }
-    let config = models::config_models::Config::new(config_ini);
-    let log_level_var = format!("aislobsterble={}", &config.log_level);
-    env::set_var("RUST_LOG", log_level_var);
-    env_logger::init();
-    log::info!("Loading config from {:?}", &config_path);
-    let mut controller = Controller::new(config);
-    controller.run();
+    let config = match models::config_models::Config::new(config_ini) {
+        Ok(config) => config,
+        Err(config_error) => {
+            init_logging("info", LogFormat::Text);
+            tracing::info!("Loading config from {:?}", &config_path);
+            tracing::error!("Failed to load config: {}", config_error);
+            process::exit(1);
+        },
+    };
+    init_logging(&config.log_level, config.log_format);
+    tracing::info!("Loading config from {:?}", &config_path);
+    if let Some(replay_path) = flag_value(&args, "--replay") {
+        match controller::replay(&config, &replay_path) {
+            Ok(ranked_plays) => ranked_plays.iter().for_each(|play| println!("{}", play)),
+            Err(failure_reason) => {
+                tracing::error!("Failed to replay {}: {}", replay_path, failure_reason);
+                process::exit(1);
+            },
+        }
+        return;
+    }
+    let mut controllers = match Controller::for_accounts(&config) {
+        Ok(controllers) => controllers,
+        Err(failure_reason) => {
+            tracing::error!("Failed to start: {}", failure_reason);
+            process::exit(1);
+        },
+    };
+    if args.iter().any(|arg| arg == "--check") {
+        for controller in controllers.iter() {
+            match controller.self_test().await {
+                Ok(ready_message) => tracing::info!("{}", ready_message),
+                Err(failure_reason) => {
+                    tracing::error!("Self test failed: {}", failure_reason);
+                    process::exit(1);
+                },
+            }
+        }
+    }
+    if let Some(game_id) = flag_value(&args, "--play") {
+        let (start, axis, letters) = match forced_play_args(&args) {
+            Ok(forced_play) => forced_play,
+            Err(failure_reason) => {
+                tracing::error!("Failed to play in game {}: {}", game_id, failure_reason);
+                process::exit(1);
+            },
+        };
+        match controllers[0].force_play(&game_id, start, axis, &letters).await {
+            Ok(result) => println!("{}", result),
+            Err(failure_reason) => {
+                tracing::error!("Failed to play in game {}: {}", game_id, failure_reason);
+                process::exit(1);
+            },
+        }
+        return;
+    }
+    Controller::run(
+        &mut controllers, config.poll_interval_seconds, config.poll_jitter_percent, config.heartbeat_polls,
+        config.long_poll_seconds
+    ).await;
 }
 
 
 
+/// Get the crate version, followed by the git hash of the source it was built from if known.
+fn version(git_hash: Option<&str>) -> String {
+    match git_hash {
+        Some(git_hash) => format!("aislobsterble {} ({})", env!("CARGO_PKG_VERSION"), git_hash),
+        None => format!("aislobsterble {}", env!("CARGO_PKG_VERSION")),
+    }
+}
+
+/// Initialize the subscriber at the given level for this crate, writing lines in the given format.
+///
+/// The RUST_LOG environment variable takes precedence over the level if it is set.
+fn init_logging(log_level: &str, log_format: LogFormat) {
+    let default_filter = format!("aislobsterble={}", log_level);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_e| EnvFilter::new(default_filter));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    match log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+/// Get the configuration file path given by a `--config` argument or the environment variable.
+///
+/// The command line argument takes precedence over the environment variable.
+fn config_path_override(args: &[String], env_config_path: Option<String>) -> Option<PathBuf> {
+    flag_value(args, "--config").or(env_config_path).map(PathBuf::from)
+}
+
+/// Get the value given for a command line flag as either `--flag VALUE` or `--flag=VALUE`.
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if arg == flag {
+            if let Some(value) = args_iter.next() {
+                return Some(value.clone());
+            }
+        } else if let Some(value) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(String::from(value));
+        }
+    }
+    None
+}
+
+/// Get the start square, axis and letters of a forced play from the `--start`, `--axis` and
+/// `--letters` flags.
+fn forced_play_args(args: &[String]) -> Result<(Coordinates, Axis, String), String> {
+    let start = flag_value(args, "--start").ok_or("Missing --start square, such as H8.")?;
+    // The board is not known until the game is fetched, so allow any column with a letter.
+    let start = Coordinates::from_algebraic(&start, MAX_ALGEBRAIC_COLUMNS)?;
+    let axis = flag_value(args, "--axis").ok_or("Missing --axis, either horizontal or vertical.")?;
+    let axis = Axis::from_name(&axis).ok_or_else(|| format!("Invalid axis {}.", axis))?;
+    let letters = flag_value(args, "--letters").ok_or("Missing --letters to play.")?;
+    Ok((start, axis, letters))
+}
+
 /// Get a path to the configuration file.
 fn get_config_path() -> PathBuf {
     let mut default_config_path = PathBuf::new();
@@ -54,4 +171,59 @@ fn get_config_path() -> PathBuf {
         config_path = default_config_path;
     }
     config_path
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_path_override() {
+        let args = |args: &[&str]| args.iter().map(|arg| String::from(*arg)).collect::<Vec<String>>();
+        let env_path = Some(String::from("/etc/env.conf"));
+        assert_eq!(config_path_override(&args(&[]), None), None);
+        assert_eq!(config_path_override(&args(&[]), env_path.clone()), Some(PathBuf::from("/etc/env.conf")));
+        assert_eq!(
+            config_path_override(&args(&["--config", "/etc/arg.conf"]), env_path.clone()),
+            Some(PathBuf::from("/etc/arg.conf"))
+        );
+        assert_eq!(
+            config_path_override(&args(&["--config=/etc/arg.conf"]), None),
+            Some(PathBuf::from("/etc/arg.conf"))
+        );
+        // A trailing flag without a path is ignored.
+        assert_eq!(config_path_override(&args(&["--config"]), env_path), Some(PathBuf::from("/etc/env.conf")));
+        assert_eq!(
+            config_path_override(&args(&["--replay", "game.json", "--config-file=x"]), None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_flag_value() {
+        let args: Vec<String> = ["--config", "a.conf", "--replay=game.json"].iter()
+            .map(|arg| String::from(*arg))
+            .collect();
+        assert_eq!(flag_value(&args, "--replay"), Some(String::from("game.json")));
+        assert_eq!(flag_value(&args, "--config"), Some(String::from("a.conf")));
+        assert_eq!(flag_value(&args, "--dry-run"), None);
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(version(None), format!("aislobsterble {}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(version(Some("1eaabfd")), format!("aislobsterble {} (1eaabfd)", env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_forced_play_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| String::from(*arg)).collect::<Vec<String>>();
+        assert_eq!(
+            forced_play_args(&args(&["--play", "12", "--start", "H8", "--axis=down", "--letters", "CAt"])),
+            Ok((Coordinates::new(7, 7), Axis::Vertical, String::from("CAt")))
+        );
+        assert!(forced_play_args(&args(&["--start", "H8", "--axis", "across"])).is_err());
+        assert!(forced_play_args(&args(&["--start", "8H", "--axis", "across", "--letters", "CAT"])).is_err());
+        assert!(forced_play_args(&args(&["--start", "H8", "--axis", "up", "--letters", "CAT"])).is_err());
+    }
+}
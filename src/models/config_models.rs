@@ -1,35 +1,416 @@
 use chrono::DateTime;
 use chrono::Utc;
 use configparser::ini::Ini;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_with::{formats::Flexible, TimestampSeconds};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
 const ALMOST_EXPIRED_THRESHOLD_SECONDS: i64 = 20;
+const LOG_LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+const ACCOUNT_SECTION_PREFIX: &str = "account:";
+const DICTIONARIES_SECTION: &str = "dictionaries";
 
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct Config {
     pub root_url: String,
+    pub proxy_url: Option<String>,
+    pub ca_cert_path: Option<String>,
+    pub filter_games_by_turn: bool,
     pub ai_display_name: String,
     pub check_score: bool,
     pub poll_interval_seconds: u32,
+    pub heartbeat_polls: Option<u32>,
+    pub poll_jitter_percent: u32,
+    pub long_poll_seconds: Option<u64>,
     pub log_level: String,
+    pub log_format: LogFormat,
     pub auth_data: AuthData,
+    pub dictionary_path: String,
+    /// The path of each dictionary that a game may name, by lower case dictionary id.
+    pub dictionary_paths: HashMap<String, String>,
+    pub rejected_words_path: Option<String>,
+    pub score_mismatch_dir: Option<String>,
+    pub ignored_game_ids: Vec<i32>,
+    pub record_path: Option<String>,
+    pub token_path: Option<String>,
+    pub score_history_path: Option<String>,
+    pub randomize_opening: bool,
+    pub opening_seed: Option<u64>,
+    pub api_paths: ApiPaths,
+    pub close_out_margin: Option<i32>,
+    pub close_out_tiles_remaining: i32,
+    pub exchange_margin: Option<i32>,
+    pub min_play_score: Option<i32>,
+    pub lookahead_candidates: Option<usize>,
+    pub failure_threshold: Option<u32>,
+    pub failure_cooldown_polls: Option<u32>,
+    pub resign_margin: Option<i32>,
+    pub resign_tiles_remaining: i32,
+    pub parallel_move_generation: bool,
+    pub max_think_millis: Option<u64>,
+    pub leave_weight: f64,
+    pub defense_weight: f64,
+    pub request_max_attempts: u32,
+    pub request_retry_base_delay_ms: u64,
+    pub request_timeout_seconds: u64,
+    pub connect_timeout_seconds: u64,
+    pub pool_idle_timeout_seconds: u64,
+    pub max_concurrent_requests: usize,
+    pub dry_run: bool,
+    pub difficulty: Difficulty,
+    pub accounts: Vec<AccountConfig>,
 }
 
 impl Config {
-    pub fn new(config_ini: Ini) -> Config {
-        let root_url = config_ini.get("slobsterble", "root_url").unwrap();
-        let username = config_ini.get("aislobsterble", "username").unwrap();
-        let password = config_ini.get("aislobsterble", "password").unwrap();
-        let check_score = config_ini.getboolcoerce("aislobsterble", "check_score")
-            .unwrap_or(Some(false)).unwrap_or(false);
-        let ai_display_name = config_ini.get("aislobsterble", "display_name").unwrap();
-        let poll_interval_seconds = config_ini
-            .getint("aislobsterble", "poll_interval_seconds")
-            .unwrap().unwrap() as u32;
-        let auth_data = AuthData { username, password };
-        let log_level = config_ini.get("aislobsterble", "log_level").unwrap();
-        Config { root_url, ai_display_name, check_score, poll_interval_seconds, log_level, auth_data }
+    pub fn new(config_ini: Ini) -> Result<Config, ConfigError> {
+        let root_url = required(config_ini.get("slobsterble", "root_url"), "slobsterble", "root_url")?;
+        let proxy_url = config_ini.get("slobsterble", "proxy_url");
+        let ca_cert_path = config_ini.get("slobsterble", "ca_cert_path");
+        let filter_games_by_turn = parsed(
+            config_ini.getboolcoerce("slobsterble", "filter_games_by_turn"), "slobsterble", "filter_games_by_turn"
+        )?.unwrap_or(false);
+        let check_score = parsed(
+            config_ini.getboolcoerce("aislobsterble", "check_score"), "aislobsterble", "check_score"
+        )?.unwrap_or(false);
+        let poll_interval_seconds = parsed(
+            config_ini.getuint("aislobsterble", "poll_interval_seconds"), "aislobsterble", "poll_interval_seconds"
+        )?;
+        let poll_interval_seconds = required(poll_interval_seconds, "aislobsterble", "poll_interval_seconds")? as u32;
+        let heartbeat_polls = parsed(
+            config_ini.getuint("aislobsterble", "heartbeat_polls"), "aislobsterble", "heartbeat_polls"
+        )?.filter(|polls| *polls > 0).map(|polls| polls as u32);
+        let poll_jitter_percent = parsed(
+            config_ini.getuint("aislobsterble", "poll_jitter_percent"), "aislobsterble", "poll_jitter_percent"
+        )?.unwrap_or(0);
+        if poll_jitter_percent > 100 {
+            return Err(ConfigError::InvalidValue {
+                section: String::from("aislobsterble"),
+                key: String::from("poll_jitter_percent"),
+                reason: String::from("expected a percentage from 0 to 100"),
+            });
+        }
+        let poll_jitter_percent = poll_jitter_percent as u32;
+        let long_poll_seconds = parsed(
+            config_ini.getuint("aislobsterble", "long_poll_seconds"), "aislobsterble", "long_poll_seconds"
+        )?.filter(|seconds| *seconds > 0);
+        let log_level = required(config_ini.get("aislobsterble", "log_level"), "aislobsterble", "log_level")?;
+        if !LOG_LEVELS.contains(&log_level.to_lowercase().as_str()) {
+            return Err(ConfigError::InvalidValue {
+                section: String::from("aislobsterble"),
+                key: String::from("log_level"),
+                reason: format!("expected one of {}", LOG_LEVELS.join(", ")),
+            });
+        }
+        let log_format = match config_ini.get("aislobsterble", "log_format") {
+            Some(log_format) => LogFormat::parse(&log_format).ok_or_else(|| ConfigError::InvalidValue {
+                section: String::from("aislobsterble"),
+                key: String::from("log_format"),
+                reason: String::from("expected one of text, json"),
+            })?,
+            None => LogFormat::Text,
+        };
+        let dictionary_path = config_ini.get("aislobsterble", "dictionary_path")
+            .unwrap_or_else(|| String::from("dictionary.txt"));
+        let dictionary_paths = match config_ini.get_map_ref().get(DICTIONARIES_SECTION) {
+            Some(section) => section.iter()
+                .map(|(dictionary_id, path)| match path {
+                    Some(path) => Ok((dictionary_id.clone(), path.clone())),
+                    None => Err(ConfigError::InvalidValue {
+                        section: String::from(DICTIONARIES_SECTION),
+                        key: dictionary_id.clone(),
+                        reason: String::from("expected a dictionary path"),
+                    }),
+                })
+                .collect::<Result<HashMap<String, String>, ConfigError>>()?,
+            None => HashMap::new(),
+        };
+        let rejected_words_path = config_ini.get("aislobsterble", "rejected_words_path");
+        let score_mismatch_dir = config_ini.get("aislobsterble", "score_mismatch_dir");
+        let ignored_game_ids = match config_ini.get("aislobsterble", "ignored_game_ids") {
+            Some(game_ids) => game_ids.split(',')
+                .map(|game_id| game_id.trim())
+                .filter(|game_id| !game_id.is_empty())
+                .map(|game_id| game_id.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_e| ConfigError::InvalidValue {
+                    section: String::from("aislobsterble"),
+                    key: String::from("ignored_game_ids"),
+                    reason: String::from("expected a comma separated list of game ids"),
+                })?,
+            None => Vec::new(),
+        };
+        let randomize_opening = parsed(
+            config_ini.getboolcoerce("aislobsterble", "randomize_opening"), "aislobsterble", "randomize_opening"
+        )?.unwrap_or(false);
+        let opening_seed = parsed(config_ini.getuint("aislobsterble", "opening_seed"), "aislobsterble", "opening_seed")?;
+        let api_paths = ApiPaths::new(&config_ini);
+        let close_out_margin = parsed(
+            config_ini.getint("aislobsterble", "close_out_margin"), "aislobsterble", "close_out_margin"
+        )?.map(|margin| margin as i32);
+        let close_out_tiles_remaining = parsed(
+            config_ini.getint("aislobsterble", "close_out_tiles_remaining"), "aislobsterble", "close_out_tiles_remaining"
+        )?.unwrap_or(7) as i32;
+        let exchange_margin = parsed(
+            config_ini.getint("aislobsterble", "exchange_margin"), "aislobsterble", "exchange_margin"
+        )?.map(|margin| margin as i32);
+        let min_play_score = parsed(
+            config_ini.getint("aislobsterble", "min_play_score"), "aislobsterble", "min_play_score"
+        )?.map(|score| score as i32);
+        let lookahead_candidates = parsed(
+            config_ini.getuint("aislobsterble", "lookahead_candidates"), "aislobsterble", "lookahead_candidates"
+        )?.filter(|count| *count > 0).map(|count| count as usize);
+        let failure_threshold = parsed(
+            config_ini.getuint("aislobsterble", "failure_threshold"), "aislobsterble", "failure_threshold"
+        )?.filter(|failures| *failures > 0).map(|failures| failures as u32);
+        let failure_cooldown_polls = parsed(
+            config_ini.getuint("aislobsterble", "failure_cooldown_polls"), "aislobsterble", "failure_cooldown_polls"
+        )?.filter(|polls| *polls > 0).map(|polls| polls as u32);
+        let resign_margin = parsed(
+            config_ini.getint("aislobsterble", "resign_margin"), "aislobsterble", "resign_margin"
+        )?.map(|margin| margin as i32);
+        let resign_tiles_remaining = parsed(
+            config_ini.getint("aislobsterble", "resign_tiles_remaining"), "aislobsterble", "resign_tiles_remaining"
+        )?.unwrap_or(0) as i32;
+        let parallel_move_generation = parsed(
+            config_ini.getboolcoerce("aislobsterble", "parallel_move_generation"),
+            "aislobsterble", "parallel_move_generation"
+        )?.unwrap_or(true);
+        let max_think_millis = parsed(
+            config_ini.getuint("aislobsterble", "max_think_millis"), "aislobsterble", "max_think_millis"
+        )?;
+        let leave_weight = parsed(config_ini.getfloat("aislobsterble", "leave_weight"), "aislobsterble", "leave_weight")?
+            .unwrap_or(1.0);
+        let defense_weight = parsed(
+            config_ini.getfloat("aislobsterble", "defense_weight"), "aislobsterble", "defense_weight"
+        )?.unwrap_or(0.0);
+        let request_max_attempts = parsed(
+            config_ini.getuint("aislobsterble", "request_max_attempts"), "aislobsterble", "request_max_attempts"
+        )?.unwrap_or(3) as u32;
+        let request_retry_base_delay_ms = parsed(
+            config_ini.getuint("aislobsterble", "request_retry_base_delay_ms"),
+            "aislobsterble", "request_retry_base_delay_ms"
+        )?.unwrap_or(500);
+        let request_timeout_seconds = parsed(
+            config_ini.getuint("aislobsterble", "request_timeout_seconds"), "aislobsterble", "request_timeout_seconds"
+        )?.unwrap_or(30);
+        let connect_timeout_seconds = parsed(
+            config_ini.getuint("aislobsterble", "connect_timeout_seconds"), "aislobsterble", "connect_timeout_seconds"
+        )?.unwrap_or(10);
+        let pool_idle_timeout_seconds = parsed(
+            config_ini.getuint("aislobsterble", "pool_idle_timeout_seconds"),
+            "aislobsterble", "pool_idle_timeout_seconds"
+        )?.unwrap_or(90);
+        let max_concurrent_requests = parsed(
+            config_ini.getuint("aislobsterble", "max_concurrent_requests"), "aislobsterble", "max_concurrent_requests"
+        )?.unwrap_or(4);
+        if max_concurrent_requests == 0 {
+            return Err(ConfigError::InvalidValue {
+                section: String::from("aislobsterble"),
+                key: String::from("max_concurrent_requests"),
+                reason: String::from("expected at least 1"),
+            });
+        }
+        let max_concurrent_requests = max_concurrent_requests as usize;
+        let dry_run = parsed(config_ini.getboolcoerce("aislobsterble", "dry_run"), "aislobsterble", "dry_run")?
+            .unwrap_or(false);
+        let difficulty = match config_ini.get("aislobsterble", "difficulty") {
+            Some(difficulty) => Difficulty::parse(&difficulty).ok_or_else(|| ConfigError::InvalidValue {
+                section: String::from("aislobsterble"),
+                key: String::from("difficulty"),
+                reason: String::from("expected one of easy, medium, hard"),
+            })?,
+            None => Difficulty::Hard,
+        };
+        let mut accounts = AccountConfig::from_account_sections(&config_ini)?;
+        if accounts.is_empty() {
+            accounts.push(AccountConfig::new(&config_ini, "aislobsterble")?);
+        }
+        let AccountConfig { ai_display_name, auth_data, record_path, token_path, score_history_path } = accounts[0].clone();
+        Ok(Config {
+            root_url, proxy_url, ca_cert_path, filter_games_by_turn, ai_display_name, check_score, poll_interval_seconds, heartbeat_polls, poll_jitter_percent, long_poll_seconds, log_level, log_format, auth_data, dictionary_path,
+            dictionary_paths, rejected_words_path, score_mismatch_dir, ignored_game_ids, record_path, token_path, score_history_path, randomize_opening, opening_seed, api_paths,
+            close_out_margin, close_out_tiles_remaining, exchange_margin, min_play_score, lookahead_candidates, failure_threshold, failure_cooldown_polls,
+            resign_margin, resign_tiles_remaining, parallel_move_generation, max_think_millis,
+            leave_weight, defense_weight, request_max_attempts, request_retry_base_delay_ms,
+            request_timeout_seconds, connect_timeout_seconds, pool_idle_timeout_seconds, max_concurrent_requests,
+            dry_run, difficulty, accounts,
+        })
+    }
+
+    /// Get the path of the dictionary with the given id, falling back to the default dictionary
+    /// if there is no id or no dictionary is configured for it.
+    pub fn dictionary_path_for(&self, dictionary_id: Option<&str>) -> &str {
+        dictionary_id
+            .and_then(|dictionary_id| self.dictionary_paths.get(&dictionary_id.to_lowercase()))
+            .unwrap_or(&self.dictionary_path)
+    }
+
+    /// Get a copy of this config that plays as the given account.
+    pub fn for_account(&self, account: &AccountConfig) -> Config {
+        Config {
+            ai_display_name: account.ai_display_name.clone(),
+            auth_data: account.auth_data.clone(),
+            record_path: account.record_path.clone(),
+            token_path: account.token_path.clone(),
+            score_history_path: account.score_history_path.clone(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The login and the per-account state files of one AI player.
+///
+/// Each `[account:NAME]` section of the config describes an account. If there are no such sections, the
+/// `[aislobsterble]` section describes the only account.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct AccountConfig {
+    pub ai_display_name: String,
+    pub auth_data: AuthData,
+    pub record_path: Option<String>,
+    pub token_path: Option<String>,
+    pub score_history_path: Option<String>,
+}
+
+impl AccountConfig {
+    fn new(config_ini: &Ini, section: &str) -> Result<AccountConfig, ConfigError> {
+        let username = required(config_ini.get(section, "username"), section, "username")?;
+        let password = required(config_ini.get(section, "password"), section, "password")?;
+        let ai_display_name = required(config_ini.get(section, "display_name"), section, "display_name")?;
+        Ok(AccountConfig {
+            ai_display_name,
+            auth_data: AuthData { username, password },
+            record_path: config_ini.get(section, "record_path"),
+            token_path: config_ini.get(section, "token_path"),
+            score_history_path: config_ini.get(section, "score_history_path"),
+        })
+    }
+
+    /// Read the accounts from the account sections, ordered by section name.
+    fn from_account_sections(config_ini: &Ini) -> Result<Vec<AccountConfig>, ConfigError> {
+        let mut sections: Vec<String> = config_ini.sections().into_iter()
+            .filter(|section| section.starts_with(ACCOUNT_SECTION_PREFIX))
+            .collect();
+        sections.sort();
+        sections.iter().map(|section| AccountConfig::new(config_ini, section)).collect()
+    }
+}
+
+/// How strongly the AI plays.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+impl Difficulty {
+    fn parse(difficulty: &str) -> Option<Difficulty> {
+        match difficulty.to_lowercase().as_str() {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// How log lines are written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, for log aggregators.
+    Json,
+}
+impl LogFormat {
+    fn parse(log_format: &str) -> Option<LogFormat> {
+        match log_format.to_lowercase().as_str() {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A problem with a setting in the config file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    MissingKey { section: String, key: String },
+    InvalidValue { section: String, key: String, reason: String },
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::MissingKey { section, key } =>
+                write!(f, "Missing required setting {} in section [{}].", key, section),
+            ConfigError::InvalidValue { section, key, reason } =>
+                write!(f, "Invalid value for setting {} in section [{}]: {}.", key, section, reason),
+        }
+    }
+}
+impl Error for ConfigError {}
+
+/// Require a setting to be present.
+fn required<T>(value: Option<T>, section: &str, key: &str) -> Result<T, ConfigError> {
+    value.ok_or_else(|| ConfigError::MissingKey { section: String::from(section), key: String::from(key) })
+}
+
+/// Convert the result of a typed Ini getter, naming the setting if its value could not be parsed.
+fn parsed<T>(result: Result<Option<T>, String>, section: &str, key: &str) -> Result<Option<T>, ConfigError> {
+    result.map_err(|reason| ConfigError::InvalidValue {
+        section: String::from(section), key: String::from(key), reason
+    })
+}
+
+#[cfg(test)]
+impl Config {
+    /// Build a config with the required settings followed by any extra settings.
+    pub fn for_tests(extra_settings: &str) -> Config {
+        let mut config_ini = Ini::new();
+        config_ini.read(format!(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n\
+            [aislobsterble]\npoll_interval_seconds=10\nusername=ai\npassword=ai\n\
+            display_name=AI\nlog_level=info\n{}",
+            extra_settings
+        )).unwrap();
+        Config::new(config_ini).unwrap()
+    }
+}
+
+/// Templates for the API paths relative to the root url.
+///
+/// Any `{game_id}` in a template is replaced by the id of the game being requested.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ApiPaths {
+    pub login: String,
+    pub refresh: String,
+    pub games: String,
+    pub game: String,
+    pub play: String,
+    pub exchange: String,
+    pub pass: String,
+    pub resign: String,
+}
+
+impl ApiPaths {
+    fn new(config_ini: &Ini) -> ApiPaths {
+        let get_path = |key: &str, default: &str| {
+            config_ini.get("slobsterble", key).unwrap_or_else(|| String::from(default))
+        };
+        ApiPaths {
+            login: get_path("login_path", "api/login"),
+            refresh: get_path("refresh_path", "api/refresh-access"),
+            games: get_path("games_path", "api/games"),
+            game: get_path("game_path", "api/game/{game_id}"),
+            play: get_path("play_path", "api/game/{game_id}"),
+            exchange: get_path("exchange_path", "api/game/{game_id}"),
+            pass: get_path("pass_path", "api/game/{game_id}"),
+            resign: get_path("resign_path", "api/game/{game_id}/resign"),
+        }
     }
 }
 
@@ -42,17 +423,13 @@ pub struct AuthData {
 
 
 #[derive(Debug)]
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct TokenPair {
     access_token: Token,
     refresh_token: Token,
 }
 
 impl TokenPair {
-    pub fn default() -> TokenPair {
-        TokenPair { access_token: Token::default(), refresh_token: Token::default() }
-    }
-
     pub fn new(refresh_token: Token, access_token: Token) -> TokenPair {
         TokenPair{ refresh_token, access_token }
     }
@@ -65,14 +442,32 @@ impl TokenPair {
         &self.access_token
     }
 
+    /// Load tokens saved by a previous run, using expired tokens if the file does not exist.
+    pub fn load(path: &str) -> Result<TokenPair, String> {
+        match fs::read_to_string(path) {
+            Ok(tokens_string) => serde_json::from_str(&tokens_string)
+                .map_err(|e| format!("Failed to parse tokens {}: {}", path, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TokenPair::default()),
+            Err(e) => Err(format!("Failed to read tokens {}: {}", path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tokens_string = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize tokens: {}", e))?;
+        fs::write(path, tokens_string)
+            .map_err(|e| format!("Failed to write tokens {}: {}", path, e))
+    }
+
 }
 
 
 #[derive(Debug)]
 #[serde_with::serde_as]
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Token {
     token: String,
+    // Written as string seconds, but read from either string or numeric seconds.
     #[serde_as(as = "TimestampSeconds<String, Flexible>")]
     expiration_date: DateTime<Utc>,
 }
@@ -84,14 +479,179 @@ impl Token {
         &self.token
     }
 
+    pub fn is_expired(&self) -> bool {
+        self.expiration_date < chrono::Utc::now()
+    }
+
     pub fn is_almost_expired(&self) -> bool {
         let now = chrono::Utc::now();
         let almost_expired_threshold_duration = chrono::Duration::seconds(ALMOST_EXPIRED_THRESHOLD_SECONDS);
         self.expiration_date < now + almost_expired_threshold_duration
     }
+}
 
+impl Default for Token {
     fn default() -> Token {
         let epoch = chrono::DateTime::<Utc>::from(std::time::UNIX_EPOCH);
         Token { token: String::from(""), expiration_date: epoch }
     }
 }
+
+#[cfg(test)]
+impl Token {
+    pub fn for_tests(token: &str, expiration_date: DateTime<Utc>) -> Token {
+        Token{ token: String::from(token), expiration_date }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_error(settings: &str) -> ConfigError {
+        let mut config_ini = Ini::new();
+        config_ini.read(String::from(settings)).unwrap();
+        Config::new(config_ini).unwrap_err()
+    }
+
+    #[test]
+    fn test_config_errors_name_the_setting() {
+        let valid_aislobsterble = "username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\n";
+        let error = config_error(&format!(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\n{}", valid_aislobsterble
+        ));
+        let expected_error = ConfigError::MissingKey {
+            section: String::from("aislobsterble"), key: String::from("poll_interval_seconds")
+        };
+        assert_eq!(error, expected_error);
+        assert_eq!(
+            config_error(&format!("[aislobsterble]\npoll_interval_seconds=10\n{}", valid_aislobsterble)),
+            ConfigError::MissingKey { section: String::from("slobsterble"), key: String::from("root_url") }
+        );
+        let error = config_error(&format!(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=soon\n{}",
+            valid_aislobsterble
+        ));
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "poll_interval_seconds"));
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=loud\n"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid value for setting log_level in section [aislobsterble]: \
+            expected one of off, error, warn, info, debug, trace."
+        );
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\npoll_jitter_percent=150\n"
+        );
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "poll_jitter_percent"));
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\nmax_concurrent_requests=0\n"
+        );
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "max_concurrent_requests"));
+    }
+
+    #[test]
+    fn test_ignored_game_ids() {
+        assert!(Config::for_tests("").ignored_game_ids.is_empty());
+        assert_eq!(Config::for_tests("ignored_game_ids=12, 15,\n").ignored_game_ids, vec![12, 15]);
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\nignored_game_ids=12,latest\n"
+        );
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "ignored_game_ids"));
+    }
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(Config::for_tests("").log_format, LogFormat::Text);
+        assert_eq!(Config::for_tests("log_format=JSON\n").log_format, LogFormat::Json);
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\nlog_format=xml\n"
+        );
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "log_format"));
+    }
+
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(Config::for_tests("").difficulty, Difficulty::Hard);
+        assert_eq!(Config::for_tests("difficulty=Easy\n").difficulty, Difficulty::Easy);
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            username=ai\npassword=ai\ndisplay_name=AI\nlog_level=info\ndifficulty=impossible\n"
+        );
+        assert!(matches!(&error, ConfigError::InvalidValue { key, .. } if key == "difficulty"));
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let now = chrono::Utc::now();
+        let expired = Token::for_tests("token", now - chrono::Duration::seconds(1));
+        assert!(expired.is_expired());
+        assert!(expired.is_almost_expired());
+        let almost_expired = Token::for_tests("token", now + chrono::Duration::seconds(5));
+        assert!(!almost_expired.is_expired());
+        assert!(almost_expired.is_almost_expired());
+        let fresh = Token::for_tests("token", now + chrono::Duration::minutes(10));
+        assert!(!fresh.is_expired());
+        assert!(!fresh.is_almost_expired());
+    }
+
+    #[test]
+    fn test_token_timestamp_formats() {
+        for expiration_date in [r#""1650000000""#, "1650000000", "1650000000.25"] {
+            let token_string = format!(r#"{{"token": "abc", "expiration_date": {}}}"#, expiration_date);
+            let token: Token = serde_json::from_str(&token_string).unwrap();
+            assert_eq!(token.token(), "abc");
+            assert_eq!(token.expiration_date.timestamp(), 1650000000);
+        }
+        let expiration_date = chrono::TimeZone::timestamp(&Utc, 1650000000, 0);
+        let snapshot = serde_json::to_string(&Token::for_tests("abc", expiration_date)).unwrap();
+        assert_eq!(snapshot, r#"{"token":"abc","expiration_date":"1650000000"}"#);
+    }
+
+    #[test]
+    fn test_dictionary_paths() {
+        let config = Config::for_tests("dictionary_path=default.txt\n[dictionaries]\nTWL=twl.txt\nsowpods=sowpods.txt\n");
+        assert_eq!(config.dictionary_paths.len(), 2);
+        assert_eq!(config.dictionary_path_for(Some("twl")), "twl.txt");
+        assert_eq!(config.dictionary_path_for(Some("SOWPODS")), "sowpods.txt");
+        assert_eq!(config.dictionary_path_for(Some("collins")), "default.txt");
+        assert_eq!(config.dictionary_path_for(None), "default.txt");
+        assert!(Config::for_tests("").dictionary_paths.is_empty());
+    }
+
+    #[test]
+    fn test_account_sections() {
+        let config = Config::for_tests("");
+        assert_eq!(config.accounts.len(), 1);
+        assert_eq!(config.accounts[0].ai_display_name, "AI");
+
+        let config = Config::for_tests(
+            "[account:second]\nusername=bob\npassword=b\ndisplay_name=Bob\ntoken_path=bob.json\nscore_history_path=bob.jsonl\n\
+            [account:first]\nusername=alice\npassword=a\ndisplay_name=Alice\n"
+        );
+        let display_names: Vec<&str> = config.accounts.iter()
+            .map(|account| account.ai_display_name.as_str())
+            .collect();
+        assert_eq!(display_names, vec!["Alice", "Bob"]);
+        let bob_config = config.for_account(&config.accounts[1]);
+        assert_eq!(bob_config.auth_data.username, "bob");
+        assert_eq!(bob_config.token_path, Some(String::from("bob.json")));
+        assert_eq!(bob_config.score_history_path, Some(String::from("bob.jsonl")));
+        assert_eq!(config.for_account(&config.accounts[0]).score_history_path, None);
+        assert_eq!(bob_config.poll_interval_seconds, 10);
+
+        let error = config_error(
+            "[slobsterble]\nroot_url=http://127.0.0.1:5000/\n[aislobsterble]\npoll_interval_seconds=10\n\
+            log_level=info\n[account:first]\nusername=alice\ndisplay_name=Alice\n"
+        );
+        assert_eq!(
+            error, ConfigError::MissingKey { section: String::from("account:first"), key: String::from("password") }
+        );
+    }
+}
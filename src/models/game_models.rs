@@ -1,12 +1,26 @@
+use std::cmp;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::fmt;
 use std::fmt::Formatter;
 use std::slice::Iter;
-use crate::models::serializers::GameSerializer;
+use std::sync::Arc;
+use crate::dictionary::Dawg;
+use crate::models::serializers::{BoardLayoutSerializer, GameSerializer};
 
-const BINGO_BONUS: i32 = 50;
-const BINGO_TILES_LENGTH: i32 = 7;
+/// The most columns that can be named by a single letter in algebraic notation.
+pub const MAX_ALGEBRAIC_COLUMNS: i32 = 26;
+const LEAVE_BLANK_BONUS: i32 = 8;
+const LEAVE_S_BONUS: i32 = 3;
+const LEAVE_DUPLICATE_PENALTY: i32 = 3;
+const LEAVE_Q_WITHOUT_U_PENALTY: i32 = 8;
+const LEAVE_IMBALANCE_PENALTY: i32 = 2;
+const BALANCED_VOWEL_RATIO: f64 = 0.4;
+const BALANCE_HIGH_VALUE: i32 = 4;
+const MIN_OPENING_TILES: usize = 2;
+const EXPOSED_WORD_MULTIPLIER_PENALTY: i32 = 10;
+const EXPOSED_LETTER_MULTIPLIER_PENALTY: i32 = 2;
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Tile {
@@ -19,6 +33,22 @@ impl Tile {
     pub fn is_blank(&self) -> bool { self.is_blank }
     pub fn get_value(&self) -> i32 { self.value }
     pub fn is_letterless(&self) -> bool { self.letter.is_none() }
+    pub fn is_vowel(&self) -> bool { matches!(self.letter, Some('A' | 'E' | 'I' | 'O' | 'U')) }
+
+    /// Get a copy of a letterless blank with its letter assigned.
+    pub fn fill_blank(&self, letter: char) -> Tile {
+        Tile{ letter: Some(letter), ..*self }
+    }
+}
+/// Show the letter, in lower case for a blank, or `?` for a blank with no letter assigned.
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.letter {
+            Some(letter) if self.is_blank => write!(f, "{}", letter.to_lowercase()),
+            Some(letter) => write!(f, "{}", letter),
+            None => write!(f, "?"),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -27,6 +57,9 @@ pub struct PlayedTile {
     tile: Tile,
 }
 impl PlayedTile {
+    pub fn new(coordinates: Coordinates, tile: Tile) -> PlayedTile {
+        PlayedTile{ coordinates, tile }
+    }
     pub fn get_coordinates_ref(&self) -> &Coordinates {
         &self.coordinates
     }
@@ -41,7 +74,7 @@ pub struct Modifier {
     word_multiplier: i32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Axis {
     Horizontal,
     Vertical,
@@ -53,16 +86,31 @@ impl Axis {
             Axis::Horizontal => Axis::Vertical,
         }
     }
+    /// The change in (row, column) for one step along the axis.
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Axis::Horizontal => (0, 1),
+            Axis::Vertical => (1, 0),
+        }
+    }
     pub fn iterator() -> Iter<'static, Axis> {
         static AXES: [Axis; 2] = [Axis::Horizontal, Axis::Vertical];
         AXES.iter()
     }
+    /// Parse an axis from its name, or `across` or `down`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Axis> {
+        match name.to_lowercase().as_str() {
+            "horizontal" | "across" => Some(Axis::Horizontal),
+            "vertical" | "down" => Some(Axis::Vertical),
+            _ => None,
+        }
+    }
 }
 impl fmt::Display for Axis {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let axis = match self {
             Axis::Horizontal => "Horizontal",
-            Axis::Vertical => "Vertical,"
+            Axis::Vertical => "Vertical",
         };
         write!(f, "{}", axis)
     }
@@ -92,6 +140,47 @@ impl Coordinates {
     }
     pub fn get_row(&self) -> i32 { self.row }
     pub fn get_column(&self) -> i32 { self.column }
+
+    /// Return the coordinates a number of steps away along an axis.
+    pub fn offset(&self, axis: &Axis, steps: i32) -> Coordinates {
+        let delta = axis.delta();
+        Coordinates{ row: self.row + delta.0 * steps, column: self.column + delta.1 * steps }
+    }
+
+    /// Format the coordinates in algebraic notation, with a column letter followed by a row number
+    /// from 1.
+    ///
+    /// Fail if the column has no letter, which is the case on boards wider than
+    /// `MAX_ALGEBRAIC_COLUMNS`.
+    pub fn to_algebraic(&self) -> Result<String, String> {
+        if self.column < 0 || self.column >= MAX_ALGEBRAIC_COLUMNS {
+            return Err(format!("Column {} has no letter in algebraic notation.", self.column));
+        }
+        let column_letter = (b'A' + self.column as u8) as char;
+        Ok(format!("{}{}", column_letter, self.row + 1))
+    }
+
+    /// Parse a square in algebraic notation, such as `H8`, on a board with the given number of columns.
+    ///
+    /// The case of the column letter is ignored. Rows are only bounded below, since the number of
+    /// rows is checked when the square is used on a board.
+    pub fn from_algebraic(s: &str, columns: i32) -> Result<Coordinates, String> {
+        let mut chars = s.chars();
+        let column_letter = match chars.next() {
+            Some(column_letter) if column_letter.is_ascii_alphabetic() => column_letter.to_ascii_uppercase(),
+            _ => return Err(format!("Square {} does not start with a column letter.", s)),
+        };
+        let column = (column_letter as u8 - b'A') as i32;
+        if column >= columns {
+            return Err(format!("Column {} is outside a board with {} columns.", column_letter, columns));
+        }
+        let row: i32 = chars.as_str().parse()
+            .map_err(|_e| format!("Square {} does not end with a row number.", s))?;
+        if row < 1 {
+            return Err(format!("Row {} is outside the board since rows are numbered from 1.", row));
+        }
+        Ok(Coordinates{ row: row - 1, column })
+    }
 }
 impl fmt::Display for Coordinates {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -99,20 +188,113 @@ impl fmt::Display for Coordinates {
     }
 }
 
-pub struct GameBoard {
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BoardError {
+    InvalidDimensions { rows: i32, columns: i32 },
+    RowOutOfBounds { row: i32, rows: i32 },
+    ColumnOutOfBounds { column: i32, columns: i32 },
+    StartOccupied { start: Coordinates },
+    InsufficientSpace { tiles: usize, axis: Axis, start: Coordinates },
+    IllegalPlay { reason: PlayError },
+    MissingLetter { coordinates: Coordinates },
+    UnsupportedLetter { letter: String },
+}
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BoardError::InvalidDimensions { rows, columns } =>
+                write!(f, "Invalid board dimensions of {} rows and {} columns.", rows, columns),
+            BoardError::RowOutOfBounds { row, rows } =>
+                write!(f, "Row {} out of bounds for board with {} rows.", row, rows),
+            BoardError::ColumnOutOfBounds { column, columns } =>
+                write!(f, "Column {} out of bounds for board with {} columns.", column, columns),
+            BoardError::StartOccupied { start } => write!(f, "Start position {} is occupied.", start),
+            BoardError::InsufficientSpace { tiles, axis, start } =>
+                write!(f, "Not enough space on the board to play {} tiles on the {} axis from {}.", tiles, axis, start),
+            BoardError::IllegalPlay { reason } => write!(f, "Illegal play: {}", reason),
+            BoardError::MissingLetter { coordinates } => write!(f, "The tile at {} has no letter.", coordinates),
+            BoardError::UnsupportedLetter { letter } =>
+                write!(f, "The tile letter {} is not a single character, which is not supported.", letter),
+        }
+    }
+}
+impl Error for BoardError {}
+
+/// The letters allowed on each empty square by the word that a tile there would form across
+/// the play axis.
+///
+/// Squares with no board tiles next to them across the axis allow any letter.
+pub struct CrossChecks {
+    allowed_letters: HashMap<Coordinates, HashSet<char>>,
+}
+impl CrossChecks {
+    pub fn allows(&self, coordinates: &Coordinates, letter: char) -> bool {
+        self.allowed_letters.get(coordinates).is_none_or(|letters| letters.contains(&letter))
+    }
+}
+
+/// A rule broken by a play.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlayError {
+    NoTiles,
+    UnassignedBlank,
+    Unavailable,
+    NotInLine,
+    NotContinuous,
+    NotOnStartSquare,
+    TooFewOpeningTiles,
+    NotConnected,
+    NoWord,
+}
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reason = match self {
+            PlayError::NoTiles => "No tiles were played.",
+            PlayError::UnassignedBlank => "A blank tile has no letter assigned.",
+            PlayError::Unavailable => "A tile was played off the board or on an occupied square.",
+            PlayError::NotInLine => "The tiles are not in a single row or column.",
+            PlayError::NotContinuous => "There is an empty square between the tiles.",
+            PlayError::NotOnStartSquare => "The opening play does not cover the start square.",
+            PlayError::TooFewOpeningTiles => "The opening play uses fewer than two tiles.",
+            PlayError::NotConnected => "The tiles do not touch any board tile.",
+            PlayError::NoWord => "The tiles do not form a word of two or more letters.",
+        };
+        write!(f, "{}", reason)
+    }
+}
+impl Error for PlayError {}
+
+/// The parts of a board that do not change during a game: its dimensions, modifiers, bingo bonus
+/// and start squares.
+#[derive(Clone)]
+pub struct BoardLayout {
     rows: i32,
     columns: i32,
-    board_tiles: Vec<Vec<Option<Tile>>>,
     modifiers: Vec<Vec<Modifier>>,
+    bingo_bonus: i32,
+    bingo_tiles_length: i32,
+    /// The squares of which the opening play must cover at least one.
+    start_squares: Vec<Coordinates>,
 }
-impl GameBoard {
-    pub fn new(game_state: &GameSerializer) -> GameBoard {
-        let rows = game_state.board_layout.rows;
-        let columns = game_state.board_layout.columns;
+impl BoardLayout {
+    /// Build the layout, failing if it has no squares or places a modifier or the start square off
+    /// the board.
+    pub fn new(board_layout: &BoardLayoutSerializer) -> Result<BoardLayout, BoardError> {
+        let rows = board_layout.rows;
+        let columns = board_layout.columns;
+        if rows <= 0 || columns <= 0 {
+            return Err(BoardError::InvalidDimensions { rows, columns });
+        }
         let mut modifier_map = HashMap::new();
-        for positioned_modifier in game_state.board_layout.modifiers.iter() {
+        for positioned_modifier in board_layout.modifiers.iter() {
             let row = positioned_modifier.row;
             let column = positioned_modifier.column;
+            if !(0..rows).contains(&row) {
+                return Err(BoardError::RowOutOfBounds { row, rows });
+            }
+            if !(0..columns).contains(&column) {
+                return Err(BoardError::ColumnOutOfBounds { column, columns });
+            }
             let letter_multiplier = positioned_modifier.modifier.letter_multiplier;
             let word_multiplier = positioned_modifier.modifier.word_multiplier;
             let modifier = Modifier{ letter_multiplier, word_multiplier };
@@ -131,13 +313,84 @@ impl GameBoard {
             }
             modifiers.push(modifier_row);
         }
+        let bingo_bonus = board_layout.bingo_bonus;
+        let bingo_tiles_length = board_layout.num_tiles;
+        let start_squares = match &board_layout.start_square {
+            Some(start_square) => {
+                if !(0..rows).contains(&start_square.row) {
+                    return Err(BoardError::RowOutOfBounds { row: start_square.row, rows });
+                }
+                if !(0..columns).contains(&start_square.column) {
+                    return Err(BoardError::ColumnOutOfBounds { column: start_square.column, columns });
+                }
+                vec![Coordinates::new(start_square.row, start_square.column)]
+            },
+            None => {
+                let marked_squares: Vec<Coordinates> = board_layout.modifiers.iter()
+                    .filter(|positioned_modifier| positioned_modifier.is_start)
+                    .map(|positioned_modifier| Coordinates::new(positioned_modifier.row, positioned_modifier.column))
+                    .collect();
+                if marked_squares.is_empty() {
+                    BoardLayout::center_squares(rows, columns)
+                } else {
+                    marked_squares
+                }
+            },
+        };
+        Ok(BoardLayout{ rows, columns, modifiers, bingo_bonus, bingo_tiles_length, start_squares })
+    }
+
+    /// Get the squares in the middle of the board.
+    ///
+    /// There is a single center square when both dimensions are odd. Otherwise no square is the
+    /// center, so the two or four squares around the middle of the board all count.
+    fn center_squares(rows: i32, columns: i32) -> Vec<Coordinates> {
+        let middle = |length: i32| if length % 2 == 0 { vec![length / 2 - 1, length / 2] } else { vec![length / 2] };
+        middle(rows).into_iter()
+            .flat_map(|row| middle(columns).into_iter().map(move |column| Coordinates::new(row, column)))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct GameBoard {
+    rows: i32,
+    columns: i32,
+    board_tiles: Vec<Vec<Option<Tile>>>,
+    layout: Arc<BoardLayout>,
+}
+impl GameBoard {
+    /// Build the board from the game state, failing if the layout has no squares, places a
+    /// modifier or the start square off the board, or a board tile has no letter.
+    ///
+    /// Letters may be any single character. This also fails if any tile in the game, including those
+    /// on the rack and in the tile distribution, has a letter of several characters such as a digraph.
+    pub fn new(game_state: &GameSerializer) -> Result<GameBoard, BoardError> {
+        let layout = BoardLayout::new(&game_state.board_layout)?;
+        GameBoard::with_layout(Arc::new(layout), game_state)
+    }
+
+    /// Build the board from a layout built earlier in the game and the tiles in the game state.
+    ///
+    /// The board layout in the game state is ignored.
+    pub fn with_layout(layout: Arc<BoardLayout>, game_state: &GameSerializer) -> Result<GameBoard, BoardError> {
+        let game_tiles = game_state.board_state.iter().map(|played_tile| &played_tile.tile)
+            .chain(game_state.rack.iter().map(|tile_count| &tile_count.tile))
+            .chain(game_state.tile_distribution.iter().map(|tile_count| &tile_count.tile));
+        for letter in game_tiles.filter_map(|tile| tile.letter.as_ref()) {
+            if letter.chars().count() > 1 {
+                return Err(BoardError::UnsupportedLetter { letter: letter.clone() });
+            }
+        }
+        let rows = layout.rows;
+        let columns = layout.columns;
         let mut played_tile_map = HashMap::new();
         for played_tile in game_state.board_state.iter() {
             let row = played_tile.row;
             let column = played_tile.column;
-            let letter = match &played_tile.tile.letter {
-                Some(letter) => Some(letter.chars().next().unwrap()),
-                None => None,
+            let letter = match played_tile.tile.letter.as_ref().and_then(|letter| letter.chars().next()) {
+                Some(letter) => Some(letter),
+                None => return Err(BoardError::MissingLetter { coordinates: Coordinates{ row, column } }),
             };
             let value = played_tile.tile.value;
             let is_blank = played_tile.tile.is_blank;
@@ -153,7 +406,17 @@ impl GameBoard {
             }
             board_tiles.push(board_tiles_row);
         }
-        GameBoard{ rows, columns, board_tiles, modifiers }
+        Ok(GameBoard{ rows, columns, board_tiles, layout })
+    }
+
+    /// Get a copy of the board with the played tiles placed on it.
+    pub fn with_play(&self, played_tiles: &[PlayedTile]) -> GameBoard {
+        let mut game_board = self.clone();
+        for played_tile in played_tiles.iter() {
+            let coordinates = &played_tile.coordinates;
+            game_board.board_tiles[coordinates.row as usize][coordinates.column as usize] = Some(played_tile.tile);
+        }
+        game_board
     }
 
     pub fn get_rows(&self) -> i32 {
@@ -163,151 +426,268 @@ impl GameBoard {
         self.columns
     }
 
-    pub fn is_occupied(&self, coordinates: &Coordinates) -> Result<bool, String> {
-        let row_bounds_err = format!("Row {} out of bounds for board with {} rows.", coordinates.row, self.rows);
-        let column_bounds_err = format!("Column {} out of bounds for board with {} columns.", coordinates.row, self.rows);
-        Ok(self.board_tiles.get(coordinates.row as usize).ok_or(row_bounds_err)?
-            .get(coordinates.column as usize).ok_or(column_bounds_err)?.is_some())
+    /// Get the board entry at the coordinates, or None if the coordinates are off the board.
+    ///
+    /// Board traversals should look up squares through this, since the coordinates are checked
+    /// against the board before they are used as indices.
+    pub fn get_tile(&self, coordinates: &Coordinates) -> Option<&Option<Tile>> {
+        if !(0..self.rows).contains(&coordinates.row) || !(0..self.columns).contains(&coordinates.column) {
+            return None;
+        }
+        Some(&self.board_tiles[coordinates.row as usize][coordinates.column as usize])
     }
 
-    /// Return true iff there is a board tile adjacent to at least one played tile.
-    pub fn is_connected(&self, played_tiles: &Vec<PlayedTile>) -> bool {
+    /// Return the empty squares of which every play must cover at least one.
+    ///
+    /// These are the empty squares next to a board tile, or the start squares on an empty board.
+    pub fn anchors(&self) -> Vec<Coordinates> {
+        if self.is_empty() {
+            return self.layout.start_squares.clone();
+        }
         let adjacency_deltas = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-        for played_tile in played_tiles {
-            for delta in adjacency_deltas {
-                let row_delta = delta.0;
-                let column_delta = delta.1;
-                let adj_row = played_tile.coordinates.row + row_delta;
-                let adj_column = played_tile.coordinates.column + column_delta;
-                let board_tiles_row = match self.board_tiles.get(adj_row as usize) {
-                    None => continue,
-                    Some(value) => value,
-                };
-                match board_tiles_row.get(adj_column as usize) {
-                    None => continue,
-                    Some(value) => {
-                        if value.is_some() {
-                            return true;
-                        }
-                    }
+        let mut anchors = Vec::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if self.board_tiles[row as usize][column as usize].is_some() {
+                    continue;
+                }
+                let is_anchor = adjacency_deltas.iter().any(|delta| {
+                    let neighbour = Coordinates{ row: row + delta.0, column: column + delta.1 };
+                    matches!(self.get_tile(&neighbour), Some(Some(_)))
+                });
+                if is_anchor {
+                    anchors.push(Coordinates{ row, column });
+                }
+            }
+        }
+        anchors
+    }
+
+    /// Find the letters that may be placed on each empty square without forming an invalid word
+    /// across the play axis.
+    pub fn cross_checks(&self, dictionary: &Dawg, axis: &Axis) -> CrossChecks {
+        let cross_axis = axis.complement();
+        let mut allowed_letters = HashMap::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let coordinates = Coordinates{ row, column };
+                if self.board_tiles[row as usize][column as usize].is_some() {
+                    continue;
+                }
+                let prefix = self.letters_from(&coordinates, &cross_axis, -1);
+                let suffix = self.letters_from(&coordinates, &cross_axis, 1);
+                if prefix.is_empty() && suffix.is_empty() {
+                    continue;
+                }
+                let letters: HashSet<char> = match dictionary.walk(&prefix) {
+                    Some(prefix_node) => dictionary.children(prefix_node).iter()
+                        .filter(|(_letter, child)| {
+                            suffix.chars()
+                                .try_fold(*child, |node, letter| dictionary.child(node, letter))
+                                .is_some_and(|node| dictionary.is_terminal(node))
+                        })
+                        .map(|(letter, _child)| *letter)
+                        .collect(),
+                    None => HashSet::new(),
                 };
+                allowed_letters.insert(coordinates, letters);
             }
         }
-        false
+        CrossChecks{ allowed_letters }
+    }
+
+    /// Read the board letters running in one direction along an axis from next to the
+    /// coordinates, in reading order.
+    fn letters_from(&self, coordinates: &Coordinates, axis: &Axis, step: i32) -> String {
+        let mut letters = Vec::new();
+        let mut position = coordinates.offset(axis, step);
+        while let Some(Some(tile)) = self.get_tile(&position) {
+            letters.push(tile.letter.expect("A blank letter was found on the board."));
+            position = position.offset(axis, step);
+        }
+        if step < 0 {
+            letters.reverse();
+        }
+        letters.into_iter().collect()
+    }
+
+    /// Return true iff no tiles have been played on the board.
+    pub fn is_empty(&self) -> bool {
+        self.board_tiles.iter().all(|board_row| board_row.iter().all(|tile| tile.is_none()))
+    }
+
+    pub fn is_occupied(&self, coordinates: &Coordinates) -> Result<bool, BoardError> {
+        if !(0..self.rows).contains(&coordinates.row) {
+            return Err(BoardError::RowOutOfBounds { row: coordinates.row, rows: self.rows });
+        }
+        if !(0..self.columns).contains(&coordinates.column) {
+            return Err(BoardError::ColumnOutOfBounds { column: coordinates.column, columns: self.columns });
+        }
+        Ok(matches!(self.get_tile(coordinates), Some(Some(_))))
+    }
+
+    /// Return true iff there is a board tile adjacent to at least one played tile.
+    ///
+    /// Adjacent squares off the board, including those at negative coordinates, are skipped.
+    pub fn is_connected(&self, played_tiles: &[PlayedTile]) -> bool {
+        let adjacency_deltas = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        played_tiles.iter().any(|played_tile| adjacency_deltas.iter().any(|delta| {
+            let neighbour = Coordinates{
+                row: played_tile.coordinates.row + delta.0,
+                column: played_tile.coordinates.column + delta.1,
+            };
+            matches!(self.get_tile(&neighbour), Some(Some(_)))
+        }))
     }
 
     /// Return true iff any gaps between played tiles are filled by board tiles.
+    ///
+    /// The played tiles may be in any order, but must be in a single row or column.
     pub fn is_continuous(&self, played_tiles: &Vec<PlayedTile>) -> bool {
-        if played_tiles.len() <= 1 {
-            return true;
-        }
-        let first = played_tiles.first().unwrap();
-        let second = played_tiles.get(1).unwrap();
-        let last = played_tiles.last().unwrap();
-        let delta = if first.coordinates.row == second.coordinates.row { (0, 1) } else { (1, 0) };
-        let mut position = first.coordinates;
-        let mut played_tiles_iter = played_tiles.iter();
-        let mut current_tile = played_tiles_iter.next();
-        while position != last.coordinates {
-            match current_tile {
-                None => return false,
-                Some(current_tile_val) => {
-                    if position == current_tile_val.coordinates {
-                        current_tile = played_tiles_iter.next();
-                    } else {
-                        let board_row = match self.board_tiles.get(position.row as usize) {
-                            None => return false,
-                            Some(val) => val,
-                        };
-                        match board_row.get(position.column as usize) {
-                            None => return false,
-                            Some(tile) => if tile.is_none() {
-                                return false;
-                            }
-                        };
-                    }
-                    position = Coordinates{
-                        row: position.row + delta.0,
-                        column: position.column + delta.1};
-                }
-            };
+        let mut coordinates: Vec<Coordinates> = played_tiles.iter()
+            .map(|played_tile| played_tile.coordinates)
+            .collect();
+        // Coordinates sort by row and then by column, so this orders the tiles along either axis.
+        coordinates.sort();
+        let (first, last) = match (coordinates.first(), coordinates.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return true,
+        };
+        let axis = if first.row == last.row {
+            Axis::Horizontal
+        } else if first.column == last.column {
+            Axis::Vertical
+        } else {
+            return false;
+        };
+        let mut position = first;
+        while position != last {
+            position = position.offset(&axis, 1);
+            if coordinates.binary_search(&position).is_err() && self.is_occupied(&position) != Ok(true) {
+                return false;
+            }
         }
         true
     }
 
-    /// Return true iff the played tiles go through the center of the board.
-    pub fn is_through_center(&self, played_tiles: &Vec<PlayedTile>) -> bool {
-        let center = Coordinates{ row: self.rows / 2, column: self.columns / 2 };
-        for tile in played_tiles.iter() {
-            if tile.coordinates == center {
-                return true;
-            }
-        }
-        false
+    /// Return true iff the played tiles cover a start square.
+    ///
+    /// The start squares are given by the layout, either directly or by marking modifiers as the
+    /// start, and are the center of the board otherwise. There may be several, so this only says
+    /// whether an opening play is placed correctly, not whether a play is the opening.
+    pub fn covers_start_square(&self, played_tiles: &[PlayedTile]) -> bool {
+        played_tiles.iter().any(|tile| self.layout.start_squares.contains(&tile.coordinates))
     }
 
     /// Return true iff all positions of played tiles are available for play.
-    pub fn is_available(&self, played_tiles: &Vec<PlayedTile>) -> bool {
-        for played_tile in played_tiles.iter() {
-            let board_row = match self.board_tiles.get(played_tile.coordinates.row as usize) {
-                None => return false,
-                Some(val) => val,
-            };
-            match board_row.get(played_tile.coordinates.column as usize) {
-                None => return false,
-                Some(val) => {
-                    if val.is_some() {
-                        return false;
-                    }
-                }
+    pub fn is_available(&self, played_tiles: &[PlayedTile]) -> bool {
+        played_tiles.iter().all(|played_tile| matches!(self.get_tile(&played_tile.coordinates), Some(None)))
+    }
+
+    /// Check the played tiles against every rule for a play, returning the first rule broken, or
+    /// the words formed and the score of a legal play.
+    ///
+    /// The words are needed to check the play, so the played tiles are sorted and indexed once for
+    /// both.
+    pub fn validate_play(
+        &self, played_tiles: &Vec<PlayedTile>, is_first_move: bool
+    ) -> Result<(Vec<String>, i32), PlayError> {
+        let first = match played_tiles.first() {
+            Some(first) => first.coordinates,
+            None => return Err(PlayError::NoTiles),
+        };
+        if played_tiles.iter().any(|played_tile| played_tile.tile.is_letterless()) {
+            return Err(PlayError::UnassignedBlank);
+        }
+        if !self.is_available(played_tiles) {
+            return Err(PlayError::Unavailable);
+        }
+        let is_in_row = played_tiles.iter().all(|played_tile| played_tile.coordinates.row == first.row);
+        let is_in_column = played_tiles.iter().all(|played_tile| played_tile.coordinates.column == first.column);
+        if !is_in_row && !is_in_column {
+            return Err(PlayError::NotInLine);
+        }
+        if !self.is_continuous(played_tiles) {
+            return Err(PlayError::NotContinuous);
+        }
+        if is_first_move {
+            if !self.covers_start_square(played_tiles) {
+                return Err(PlayError::NotOnStartSquare);
             }
+            if played_tiles.len() < MIN_OPENING_TILES {
+                return Err(PlayError::TooFewOpeningTiles);
+            }
+        } else if !self.is_connected(played_tiles) {
+            return Err(PlayError::NotConnected);
         }
-        true
+        let played_tiles = &GameBoard::sorted(played_tiles);
+        let played_tile_map = GameBoard::played_tile_map(played_tiles);
+        let primary_axis = self.primary_axis(played_tiles);
+        let words = self.sorted_words_created(played_tiles, &played_tile_map, primary_axis)
+            .map_err(|_| PlayError::UnassignedBlank)?;
+        if !words.iter().any(|word| word.chars().count() >= 2) {
+            return Err(PlayError::NoWord);
+        }
+        let score = self.sorted_score(played_tiles, &played_tile_map, primary_axis);
+        Ok((words, score))
     }
 
     pub fn build_played_tiles(
             &self, start_coordinates: &Coordinates, tiles: Vec<&Tile>, axis: &Axis
-    ) -> Result<Vec<PlayedTile>, String> {
+    ) -> Result<Vec<PlayedTile>, BoardError> {
         let mut played_tiles = Vec::new();
-        let mut position = start_coordinates.clone();
-        let delta = match axis {
-            Axis::Horizontal => (0, 1),
-            Axis::Vertical => (1, 0),
+        let mut position = *start_coordinates;
+        let insufficient_space_err = BoardError::InsufficientSpace {
+            tiles: tiles.len(), axis: *axis, start: *start_coordinates
         };
-        let row_limit_err = format!(
-            "Not enough rows on the board to play {} tiles on the {} axis from {}",
-            tiles.len(), &axis, &start_coordinates
-        );
-        let column_limit_err = format!(
-            "Not enough columns on the board to play {} tiles on the {} axis from {}",
-            tiles.len(), &axis, &start_coordinates
-        );
         for (tile_index, tile) in tiles.iter().enumerate() {
-            let mut board_row = self.board_tiles.get(position.row as usize).ok_or(&row_limit_err)?;
-            let mut board_tile = board_row.get(position.column as usize).ok_or(&column_limit_err)?;
-            if board_tile.is_some() && tile_index == 0 {
-                return Err(format!("Start position {} is occupied", &start_coordinates));
-            }
-            while board_tile.is_some() {
-                position = Coordinates{ row: position.row + delta.0, column: position.column + delta.1 };
-                board_row = self.board_tiles.get(position.row as usize).ok_or(&row_limit_err)?;
-                board_tile = board_row.get(position.column as usize).ok_or(&column_limit_err)?;
+            if self.is_occupied(&position).map_err(|_| insufficient_space_err.clone())? {
+                if tile_index == 0 {
+                    return Err(BoardError::StartOccupied { start: *start_coordinates });
+                }
+                while self.is_occupied(&position).map_err(|_| insufficient_space_err.clone())? {
+                    position = position.offset(axis, 1);
+                }
             }
-            played_tiles.push(PlayedTile{ coordinates: position.clone(), tile: *tile.clone()});
-            position = Coordinates{ row: position.row + delta.0, column: position.column + delta.1 };
+            played_tiles.push(PlayedTile{ coordinates: position, tile: **tile });
+            position = position.offset(axis, 1);
         }
         Ok(played_tiles)
     }
 
-    fn played_tile_map(played_tiles: &Vec<PlayedTile>) -> HashMap<Coordinates, &PlayedTile> {
+    /// Get the words formed and the score of playing the tiles in order along the axis from the
+    /// start, skipping over any board tiles in the way.
+    ///
+    /// Blanks must already have their letters. The words are not checked against a dictionary.
+    pub fn evaluate(&self, start: Coordinates, tiles: &[Tile], axis: Axis) -> Result<(Vec<String>, i32), BoardError> {
+        let played_tiles = self.build_played_tiles(&start, tiles.iter().collect(), &axis)?;
+        self.validate_play(&played_tiles, self.is_empty())
+            .map_err(|reason| BoardError::IllegalPlay { reason })
+    }
+
+    /// Copy the played tiles in order of row and then column, which is their order along the play axis.
+    fn sorted(played_tiles: &[PlayedTile]) -> Vec<PlayedTile> {
+        let mut sorted = played_tiles.to_vec();
+        sorted.sort();
+        sorted
+    }
+
+    /// Index the played tiles by their coordinates, to be built once per play and shared by word
+    /// building and scoring.
+    fn played_tile_map(played_tiles: &[PlayedTile]) -> HashMap<Coordinates, &PlayedTile> {
         let mut played_tile_map: HashMap<Coordinates, &PlayedTile> = HashMap::new();
         for played_tile in played_tiles.iter() {
-            played_tile_map.insert(played_tile.coordinates.clone(), &played_tile);
+            played_tile_map.insert(played_tile.coordinates, played_tile);
         }
         played_tile_map
     }
 
-    pub fn build_word(&self, start: Coordinates, end: Coordinates, played_tile_map: &HashMap<Coordinates, &PlayedTile>) -> String {
+    /// Read the word from the start to the end coordinates inclusive, from board tiles and played tiles.
+    ///
+    /// Fail if a tile in the word has no letter.
+    pub fn build_word(
+        &self, start: Coordinates, end: Coordinates, played_tile_map: &HashMap<Coordinates, &PlayedTile>
+    ) -> Result<String, BoardError> {
         let axis = if start.row == end.row { Axis::Horizontal } else { Axis::Vertical };
         let delta = match axis {
             Axis::Horizontal => (0, 1),
@@ -317,36 +697,58 @@ impl GameBoard {
         let mut handled_inclusive = false;
         let mut word = String::new();
         while position != end || !handled_inclusive {
-            let letter = match self.board_tiles.get(position.row as usize).unwrap().get(position.column as usize).unwrap() {
-                Some(tile) => tile.letter.expect("A blank letter was found on the board."),
-                None => played_tile_map.get(&position).expect("No played tile in empty board space in iteration bounds for building a word.").tile.letter.expect("A blank letter was played."),
+            let tile = match self.get_tile(&position).expect("Word bounds are on the board.") {
+                Some(tile) => tile,
+                None => &played_tile_map.get(&position).expect("No played tile in empty board space in iteration bounds for building a word.").tile,
             };
-            word.push(letter);
+            word.push(tile.letter.ok_or(BoardError::MissingLetter { coordinates: position })?);
             if position == end {
                 handled_inclusive = true;
             } else {
                 position = Coordinates{ row: position.row + delta.0, column: position.column + delta.1 };
             }
         }
-        word
+        Ok(word)
+    }
+
+    /// Get the word along the play axis followed by any words formed across it, in board order.
+    ///
+    /// Fail if a tile in any of the words has no letter.
+    pub fn words_created(&self, played_tiles: &[PlayedTile]) -> Result<Vec<String>, BoardError> {
+        let played_tiles = &GameBoard::sorted(played_tiles);
+        let played_tile_map = GameBoard::played_tile_map(played_tiles);
+        self.sorted_words_created(played_tiles, &played_tile_map, self.primary_axis(played_tiles))
     }
 
-    pub fn words_created(&self, played_tiles: &Vec<PlayedTile>) -> Vec<String> {
+    /// Get the words formed and the score of a play of at least one tile.
+    ///
+    /// This sorts and indexes the played tiles once for both, so prefer it to calling
+    /// `words_created` and `score` separately.
+    pub fn words_and_score(&self, played_tiles: &[PlayedTile]) -> Result<(Vec<String>, i32), BoardError> {
+        let played_tiles = &GameBoard::sorted(played_tiles);
+        let played_tile_map = GameBoard::played_tile_map(played_tiles);
         let primary_axis = self.primary_axis(played_tiles);
+        let words = self.sorted_words_created(played_tiles, &played_tile_map, primary_axis)?;
+        Ok((words, self.sorted_score(played_tiles, &played_tile_map, primary_axis)))
+    }
+
+    /// Get the words formed by played tiles that are already sorted and indexed.
+    fn sorted_words_created(
+        &self, played_tiles: &[PlayedTile], played_tile_map: &HashMap<Coordinates, &PlayedTile>, primary_axis: Axis
+    ) -> Result<Vec<String>, BoardError> {
         let secondary_axis = primary_axis.complement();
-        let played_tile_map = GameBoard::played_tile_map(played_tiles);
         let mut words = Vec::new();
-        let primary_start = self.min_connected_position(&played_tiles.first().unwrap().coordinates, &played_tile_map, &primary_axis);
-        let primary_end = self.max_connected_position(&played_tiles.last().unwrap().coordinates, &played_tile_map, &primary_axis);
-        words.push(self.build_word(primary_start, primary_end, &played_tile_map));
+        let primary_start = self.min_connected_position(&played_tiles.first().unwrap().coordinates, played_tile_map, &primary_axis);
+        let primary_end = self.max_connected_position(&played_tiles.last().unwrap().coordinates, played_tile_map, &primary_axis);
+        words.push(self.build_word(primary_start, primary_end, played_tile_map)?);
         for played_tile in played_tiles.iter() {
-            let start = self.min_connected_position(&played_tile.coordinates, &played_tile_map, &secondary_axis);
-            let end = self.max_connected_position(&played_tile.coordinates, &played_tile_map, &secondary_axis);
+            let start = self.min_connected_position(&played_tile.coordinates, played_tile_map, &secondary_axis);
+            let end = self.max_connected_position(&played_tile.coordinates, played_tile_map, &secondary_axis);
             if start != end {
-                words.push(self.build_word(start, end, &played_tile_map));
+                words.push(self.build_word(start, end, played_tile_map)?);
             }
         }
-        words
+        Ok(words)
     }
 
     fn primary_axis(&self, played_tiles: &Vec<PlayedTile>) -> Axis {
@@ -376,40 +778,55 @@ impl GameBoard {
         }
     }
 
-    pub fn score(&self, played_tiles: &Vec<PlayedTile>) -> i32 {
+    pub fn score(&self, played_tiles: &[PlayedTile]) -> i32 {
         if played_tiles.len() == 0 {
             return 0;
         }
+        let played_tiles = &GameBoard::sorted(played_tiles);
+        let played_tile_map = GameBoard::played_tile_map(played_tiles);
+        self.sorted_score(played_tiles, &played_tile_map, self.primary_axis(played_tiles))
+    }
+
+    /// Score played tiles that are already sorted and indexed.
+    ///
+    /// Only the opening play, on an empty board, forms no words across its axis. Boards can have
+    /// several start squares, so covering one does not make a play the opening.
+    fn sorted_score(
+        &self, played_tiles: &[PlayedTile], played_tile_map: &HashMap<Coordinates, &PlayedTile>, primary_axis: Axis
+    ) -> i32 {
         let mut total = 0;
-        let primary_axis = self.primary_axis(played_tiles);
         let secondary_axis = primary_axis.complement();
-        total += self.score_axis(played_tiles, &played_tiles[0].coordinates, &primary_axis);
-        if !self.is_through_center(played_tiles) {
+        let is_first_move = self.is_empty();
+        total += self.score_axis(
+            played_tiles, played_tile_map, &played_tiles[0].coordinates, &primary_axis, is_first_move
+        );
+        if !is_first_move {
             for played_tile in played_tiles.iter() {
-                total += self.score_axis(played_tiles, &played_tile.coordinates, &secondary_axis);
+                total += self.score_axis(
+                    played_tiles, played_tile_map, &played_tile.coordinates, &secondary_axis, is_first_move
+                );
             }
         }
-        if played_tiles.len() as i32 == BINGO_TILES_LENGTH {
-            total += BINGO_BONUS;
+        if played_tiles.len() as i32 == self.layout.bingo_tiles_length {
+            total += self.layout.bingo_bonus;
         }
         return total;
     }
 
-    fn score_axis(&self, played_tiles: &Vec<PlayedTile>, base_coordinates: &Coordinates, axis: &Axis) -> i32 {
+    fn score_axis(
+        &self, played_tiles: &[PlayedTile], played_tile_map: &HashMap<Coordinates, &PlayedTile>,
+        base_coordinates: &Coordinates, axis: &Axis, is_first_move: bool
+    ) -> i32 {
         let mut total = 0;
         let mut word_multiplier = 1;
         if played_tiles.len() == 0 {
             return 0;
         }
-        let mut played_tile_map: HashMap<Coordinates, &PlayedTile> = HashMap::new();
-        for played_tile in played_tiles.iter() {
-            played_tile_map.insert(played_tile.coordinates.clone(), &played_tile);
-        }
-        let coordinate_min = self.min_connected_position(base_coordinates, &played_tile_map, &axis);
-        let coordinate_max = self.max_connected_position(base_coordinates, &played_tile_map, &axis);
+        let coordinate_min = self.min_connected_position(base_coordinates, played_tile_map, axis);
+        let coordinate_max = self.max_connected_position(base_coordinates, played_tile_map, axis);
         if coordinate_min == coordinate_max {
-            if self.is_through_center(played_tiles) {
-                let modifier = self.modifiers[coordinate_min.row as usize][coordinate_max.column as usize];
+            if is_first_move {
+                let modifier = self.layout.modifiers[coordinate_min.row as usize][coordinate_max.column as usize];
                 return played_tiles[0].tile.value * modifier.word_multiplier * modifier.letter_multiplier;
             }
             return 0;
@@ -420,14 +837,14 @@ impl GameBoard {
         let mut position = coordinate_min.clone();
         let mut handled_inclusive = false;
         while position != coordinate_max || !handled_inclusive {
-            let board_tile = self.board_tiles[position.row as usize][position.column as usize];
+            let board_tile = *self.get_tile(&position).expect("Word bounds are on the board.");
             match board_tile {
                 Some(board_tile) => {
                     total += board_tile.value;
                 },
                 None => {
                     let played_tile = played_tile_map.get(&position).unwrap();
-                    let modifier = self.modifiers[position.row as usize][position.column as usize];
+                    let modifier = self.layout.modifiers[position.row as usize][position.column as usize];
                     total += played_tile.tile.value * modifier.letter_multiplier;
                     word_multiplier *= modifier.word_multiplier;
                 },
@@ -442,6 +859,126 @@ impl GameBoard {
         return total;
     }
 
+    /// Count the empty squares next to the played tiles that remain open for future plays.
+    ///
+    /// A lower count means the play leaves fewer lanes for the opponent to build from.
+    pub fn open_neighbour_count(&self, played_tiles: &[PlayedTile]) -> usize {
+        self.open_neighbours(played_tiles).len()
+    }
+
+    /// Estimate the value to the opponent of the premium squares left open next to the played tiles.
+    ///
+    /// Word multipliers are worth more than letter multipliers since they apply to a whole word,
+    /// which could include the played tiles.
+    pub fn exposed_premium_value(&self, played_tiles: &[PlayedTile]) -> i32 {
+        self.open_neighbours(played_tiles).iter()
+            .map(|neighbour| {
+                let modifier = &self.layout.modifiers[neighbour.row as usize][neighbour.column as usize];
+                (modifier.word_multiplier - 1) * EXPOSED_WORD_MULTIPLIER_PENALTY
+                    + (modifier.letter_multiplier - 1) * EXPOSED_LETTER_MULTIPLIER_PENALTY
+            })
+            .sum()
+    }
+
+    fn open_neighbours(&self, played_tiles: &[PlayedTile]) -> HashSet<Coordinates> {
+        let adjacency_deltas = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        let mut open_neighbours = HashSet::new();
+        for played_tile in played_tiles.iter() {
+            for delta in adjacency_deltas {
+                let neighbour = Coordinates{
+                    row: played_tile.coordinates.row + delta.0,
+                    column: played_tile.coordinates.column + delta.1,
+                };
+                let is_played = played_tiles.iter().any(|other| other.coordinates == neighbour);
+                if !is_played && self.is_occupied(&neighbour) == Ok(false) {
+                    open_neighbours.insert(neighbour);
+                }
+            }
+        }
+        open_neighbours
+    }
+
+    /// Find the best scoring legal placement of a specific word using tiles from the rack.
+    ///
+    /// Return the start coordinates, axis, and score of the placement, or None if the word
+    /// cannot be legally played.
+    pub fn can_play_word(
+        &self, word: &str, rack: &Rack, dictionary: &Dawg
+    ) -> Option<(Coordinates, Axis, i32)> {
+        let letters: Vec<char> = word.to_uppercase().chars().collect();
+        if letters.is_empty() {
+            return None;
+        }
+        let is_first_move = self.is_empty();
+        let mut best_placement: Option<(Coordinates, Axis, i32)> = None;
+        for axis in Axis::iterator() {
+            let delta = match axis {
+                Axis::Horizontal => (0, 1),
+                Axis::Vertical => (1, 0),
+            };
+            for start_row in 0..self.rows {
+                for start_column in 0..self.columns {
+                    let start = Coordinates{ row: start_row, column: start_column };
+                    let offset = letters.len() as i32 - 1;
+                    let end = Coordinates{ row: start.row + delta.0 * offset, column: start.column + delta.1 * offset };
+                    let before = Coordinates{ row: start.row - delta.0, column: start.column - delta.1 };
+                    let after = Coordinates{ row: end.row + delta.0, column: end.column + delta.1 };
+                    // The word must fit on the board and must not be extended by board tiles.
+                    if self.is_occupied(&end).is_err()
+                        || self.is_occupied(&before).unwrap_or(false)
+                        || self.is_occupied(&after).unwrap_or(false) {
+                        continue;
+                    }
+                    let played_tiles = match self.place_word(&letters, &start, delta, rack) {
+                        Some(played_tiles) => played_tiles,
+                        None => continue,
+                    };
+                    let score = match self.validate_play(&played_tiles, is_first_move) {
+                        Ok((words, score)) if words.iter().all(|word| dictionary.contains(word)) => score,
+                        _ => continue,
+                    };
+                    if best_placement.is_none_or(|(_start, _axis, best_score)| score > best_score) {
+                        best_placement = Some((start, *axis, score));
+                    }
+                }
+            }
+        }
+        best_placement
+    }
+
+    /// Lay out the letters of a word from a start position, taking tiles from the rack for
+    /// every empty square.
+    ///
+    /// Matching lettered tiles are used before letterless blanks. Return None if a board tile
+    /// conflicts with the word, the word runs off the board, or the rack runs out of usable tiles.
+    fn place_word(
+        &self, letters: &[char], start: &Coordinates, delta: (i32, i32), rack: &Rack
+    ) -> Option<Vec<PlayedTile>> {
+        let mut remaining_tiles = rack.tiles.clone();
+        let mut played_tiles = Vec::new();
+        for (index, letter) in letters.iter().enumerate() {
+            let coordinates = Coordinates{
+                row: start.row + delta.0 * index as i32,
+                column: start.column + delta.1 * index as i32,
+            };
+            if let Some(board_tile) = self.get_tile(&coordinates)? {
+                if board_tile.letter != Some(*letter) {
+                    return None;
+                }
+                continue;
+            }
+            let tile_index = remaining_tiles.iter().position(|tile| tile.letter == Some(*letter))
+                .or_else(|| remaining_tiles.iter().position(|tile| tile.is_letterless()))?;
+            let rack_tile = remaining_tiles.remove(tile_index);
+            let tile = Tile{ letter: Some(*letter), is_blank: rack_tile.is_blank, value: rack_tile.value };
+            played_tiles.push(PlayedTile{ coordinates, tile });
+        }
+        if played_tiles.is_empty() {
+            return None;
+        }
+        Some(played_tiles)
+    }
+
     fn min_connected_position(&self, start_position: &Coordinates, played_tile_map: &HashMap<Coordinates, &PlayedTile>, axis: &Axis) -> Coordinates {
         self.extremal_connected_position(start_position, played_tile_map, axis, Direction::Negative)
     }
@@ -456,25 +993,44 @@ impl GameBoard {
         let mut extremal_position = start_position.clone();
         let mut adj_position = Coordinates{ row: extremal_position.row + delta.0, column: extremal_position.column + delta.1 };
         loop {
-            let board_row = match self.board_tiles.get(adj_position.row as usize) {
+            match self.get_tile(&adj_position) {
                 None => return extremal_position,
-                Some(board_row_val) => board_row_val,
-            };
-            match board_row.get(adj_position.column as usize) {
-                None => return extremal_position,
-                Some(board_entry) => {
-                    if board_entry.is_none() && !played_tile_map.contains_key(&adj_position) {
-                        return extremal_position;
-                    } else {
-                        extremal_position = adj_position;
-                        adj_position = Coordinates{ row: extremal_position.row + delta.0, column: extremal_position.column + delta.1 };
-                    }
-                }
+                Some(None) if !played_tile_map.contains_key(&adj_position) => return extremal_position,
+                Some(_) => {
+                    extremal_position = adj_position;
+                    adj_position = Coordinates{ row: extremal_position.row + delta.0, column: extremal_position.column + delta.1 };
+                },
             }
         }
     }
 }
 
+/// Draw the board as a grid with column letters across the top and row numbers down the side.
+///
+/// Tiles are shown by letter, with blanks in lower case. Empty modifier squares show their
+/// multiplier, such as `3W` or `2L`, and other empty squares show a dot.
+impl fmt::Display for GameBoard {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "  ")?;
+        for column in 0..self.columns {
+            write!(f, "{:>3}", (b'A' + column as u8) as char)?;
+        }
+        for (row, (tiles_row, modifiers_row)) in self.board_tiles.iter().zip(self.layout.modifiers.iter()).enumerate() {
+            write!(f, "\n{:>2}", row + 1)?;
+            for (tile, modifier) in tiles_row.iter().zip(modifiers_row.iter()) {
+                let square = match tile {
+                    Some(tile) => tile.to_string(),
+                    None if modifier.word_multiplier > 1 => format!("{}W", modifier.word_multiplier),
+                    None if modifier.letter_multiplier > 1 => format!("{}L", modifier.letter_multiplier),
+                    None => String::from("."),
+                };
+                write!(f, "{:>3}", square)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct Rack {
     pub tiles: Vec<Tile>,
 }
@@ -482,10 +1038,7 @@ impl Rack {
     pub fn new(game_state: &GameSerializer) -> Rack {
         let mut tiles = Vec::new();
         for tile_count in game_state.rack.iter() {
-            let letter = match &tile_count.tile.letter {
-                Some(letter) => Some(letter.chars().next().unwrap()),
-                None => None
-            };
+            let letter = tile_count.tile.letter.as_ref().and_then(|letter| letter.chars().next());
             let is_blank = tile_count.tile.is_blank;
             let value = tile_count.tile.value;
             let tile = Tile{ letter, is_blank, value };
@@ -495,23 +1048,168 @@ impl Rack {
         }
         Rack{ tiles }
     }
+}
+impl fmt::Display for Rack {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let tiles: Vec<String> = self.tiles.iter().map(|tile| tile.to_string()).collect();
+        write!(f, "{}", tiles.join(" "))
+    }
+}
 
-    pub fn fill_blanks(&self, letter_fills: &Vec<char>) -> Rack {
-        let blank_count = self.tiles.iter().filter(|tile| tile.is_letterless()).count();
-        if letter_fills.len() != blank_count {
-            panic!("Mismatch in number of blanks and letter fillers.");
+/// Every tile in the game, as given by the game's tile distribution.
+pub struct TileBag {
+    tiles: Vec<Tile>,
+}
+impl TileBag {
+    pub fn new(game_state: &GameSerializer) -> TileBag {
+        let mut tiles = Vec::new();
+        for tile_count in game_state.tile_distribution.iter() {
+            let letter = tile_count.tile.letter.as_ref().and_then(|letter| letter.chars().next());
+            let tile = Tile{ letter, is_blank: tile_count.tile.is_blank, value: tile_count.tile.value };
+            for _ in 0..tile_count.count {
+                tiles.push(tile);
+            }
         }
-        let mut tiles: Vec<Tile> = Vec::new();
-        let mut fill_index = 0;
-        for tile in &self.tiles {
-            if tile.is_letterless() {
-                tiles.push(Tile{ letter: Some(letter_fills[fill_index]), is_blank: tile.is_blank, value: tile.value });
-                fill_index += 1;
+        TileBag{ tiles }
+    }
+
+    /// Return true iff the game did not give a tile distribution.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Get the tiles that are neither on the board nor on the rack.
+    ///
+    /// These are the tiles left in the bag together with the tiles on the other players' racks.
+    /// Blanks are letterless, since a blank on the board accounts for any unseen blank.
+    pub fn unseen_tiles(&self, game_board: &GameBoard, rack: &Rack) -> Vec<Tile> {
+        let mut unseen = self.tiles.clone();
+        let board_tiles = game_board.board_tiles.iter().flatten().flatten();
+        for seen_tile in board_tiles.chain(rack.tiles.iter()) {
+            let position = unseen.iter().position(|tile| if seen_tile.is_blank {
+                tile.is_blank
             } else {
-                tiles.push(tile.clone());
+                !tile.is_blank && tile.letter == seen_tile.letter
+            });
+            if let Some(position) = position {
+                unseen.swap_remove(position);
             }
         }
-        Rack{ tiles }
+        unseen.sort();
+        unseen
+    }
+}
+
+/// Estimate the worth, in points, of keeping the tiles on the rack for the next turn.
+///
+/// Blanks and S tiles are worth keeping. Duplicate letters, a Q without a U, and more than one
+/// extra consonant or vowel make the next draw less likely to produce a good play.
+pub fn leave_value(leave: &[Tile]) -> i32 {
+    let mut letter_counts: HashMap<char, i32> = HashMap::new();
+    for letter in leave.iter().filter_map(|tile| tile.get_letter()) {
+        *letter_counts.entry(letter).or_insert(0) += 1;
+    }
+    let blank_count = leave.iter().filter(|tile| tile.is_letterless()).count() as i32;
+    let s_count = letter_counts.get(&'S').copied().unwrap_or(0);
+    let duplicate_count: i32 = letter_counts.values().map(|count| count - 1).sum();
+    let vowel_count = leave.iter().filter(|tile| tile.is_vowel()).count() as i32;
+    let consonant_count = letter_counts.values().sum::<i32>() - vowel_count;
+    let imbalance = cmp::max((consonant_count - vowel_count).abs() - 1, 0);
+
+    let mut value = blank_count * LEAVE_BLANK_BONUS + s_count * LEAVE_S_BONUS;
+    value -= duplicate_count * LEAVE_DUPLICATE_PENALTY;
+    value -= imbalance * LEAVE_IMBALANCE_PENALTY;
+    if letter_counts.contains_key(&'Q') && !letter_counts.contains_key(&'U') {
+        value -= LEAVE_Q_WITHOUT_U_PENALTY;
+    }
+    value
+}
+
+/// Score how well balanced the tiles are as a rack to draw to, where 1 is best.
+///
+/// This loses a point for each step of the share of vowels among the lettered tiles away from
+/// two in five, for each extra copy of a tile worth 4 or more points, and for a Q without a U.
+pub fn rack_balance(tiles: &[Tile]) -> f64 {
+    let lettered_tiles: Vec<&Tile> = tiles.iter().filter(|tile| !tile.is_letterless()).collect();
+    if lettered_tiles.is_empty() {
+        return 1.0;
+    }
+    let vowel_count = lettered_tiles.iter().filter(|tile| tile.is_vowel()).count();
+    let vowel_ratio = vowel_count as f64 / lettered_tiles.len() as f64;
+    let mut balance = 1.0 - (vowel_ratio - BALANCED_VOWEL_RATIO).abs() / BALANCED_VOWEL_RATIO;
+    let mut letter_counts: HashMap<char, i32> = HashMap::new();
+    for tile in lettered_tiles.iter().filter(|tile| tile.get_value() >= BALANCE_HIGH_VALUE) {
+        *letter_counts.entry(tile.get_letter().unwrap()).or_insert(0) += 1;
+    }
+    balance -= letter_counts.values().map(|count| count - 1).sum::<i32>() as f64;
+    let has_letter = |letter: char| lettered_tiles.iter().any(|tile| tile.get_letter() == Some(letter));
+    if has_letter('Q') && !has_letter('U') {
+        balance -= 1.0;
+    }
+    balance
+}
+
+/// Build a board for tests without assembling a game state from the server.
+///
+/// The board starts empty with unit modifiers and a 50 point bonus for playing 7 tiles.
+#[cfg(test)]
+pub struct GameBoardBuilder {
+    board_tiles: Vec<Vec<Option<Tile>>>,
+    layout: BoardLayout,
+}
+
+#[cfg(test)]
+impl GameBoardBuilder {
+    pub fn new(rows: i32, columns: i32) -> GameBoardBuilder {
+        let unit_modifier = Modifier{ letter_multiplier: 1, word_multiplier: 1 };
+        GameBoardBuilder{
+            board_tiles: vec![vec![None; columns as usize]; rows as usize],
+            layout: BoardLayout{
+                rows,
+                columns,
+                modifiers: vec![vec![unit_modifier; columns as usize]; rows as usize],
+                bingo_bonus: 50,
+                bingo_tiles_length: 7,
+                start_squares: BoardLayout::center_squares(rows, columns),
+            },
+        }
+    }
+
+    pub fn tile(mut self, row: i32, column: i32, letter: char, value: i32) -> GameBoardBuilder {
+        self.board_tiles[row as usize][column as usize] = Some(Tile{ letter: Some(letter), is_blank: false, value });
+        self
+    }
+
+    /// Place the letters of the word along the axis from the start square, each worth one point.
+    pub fn word(mut self, row: i32, column: i32, axis: Axis, word: &str) -> GameBoardBuilder {
+        for (index, letter) in word.chars().enumerate() {
+            let (letter_row, letter_column) = match axis {
+                Axis::Horizontal => (row, column + index as i32),
+                Axis::Vertical => (row + index as i32, column),
+            };
+            self = self.tile(letter_row, letter_column, letter, 1);
+        }
+        self
+    }
+
+    pub fn modifier(mut self, row: i32, column: i32, letter_multiplier: i32, word_multiplier: i32) -> GameBoardBuilder {
+        self.layout.modifiers[row as usize][column as usize] = Modifier{ letter_multiplier, word_multiplier };
+        self
+    }
+
+    pub fn bingo(mut self, bingo_bonus: i32, bingo_tiles_length: i32) -> GameBoardBuilder {
+        self.layout.bingo_bonus = bingo_bonus;
+        self.layout.bingo_tiles_length = bingo_tiles_length;
+        self
+    }
+
+    pub fn build(self) -> GameBoard {
+        GameBoard{
+            rows: self.layout.rows,
+            columns: self.layout.columns,
+            board_tiles: self.board_tiles,
+            layout: Arc::new(self.layout),
+        }
     }
 }
 
@@ -519,6 +1217,43 @@ impl Rack {
 mod tests {
     use super::*;
 
+    fn test_board(rows: i32, columns: i32, board_letters: &[(i32, i32, char)]) -> GameBoard {
+        board_letters.iter()
+            .fold(GameBoardBuilder::new(rows, columns), |builder, (row, column, letter)| builder.tile(*row, *column, *letter, 1))
+            .build()
+    }
+
+    fn test_rack(letters: &str) -> Rack {
+        let tiles = letters.chars().map(|letter| match letter {
+            '?' => Tile{ letter: None, is_blank: true, value: 0 },
+            _ => Tile{ letter: Some(letter), is_blank: false, value: 1 },
+        }).collect();
+        Rack{ tiles }
+    }
+
+    fn test_played_tile(row: i32, column: i32, letter: char) -> PlayedTile {
+        let tile = Tile{ letter: Some(letter), is_blank: false, value: 1 };
+        PlayedTile{ coordinates: Coordinates{ row, column }, tile }
+    }
+
+    fn scoring_value(letter: char) -> i32 {
+        match letter {
+            'C' => 3,
+            'H' => 4,
+            'X' => 8,
+            _ => 1,
+        }
+    }
+
+    fn scoring_played_tile(row: i32, column: i32, letter: char) -> PlayedTile {
+        let tile = Tile{ letter: Some(letter), is_blank: false, value: scoring_value(letter) };
+        PlayedTile{ coordinates: Coordinates{ row, column }, tile }
+    }
+
+    fn test_dictionary(words: &[&str]) -> Dawg {
+        Dawg::new(words)
+    }
+
     #[test]
     fn test_tile_order() {
         let none_blank_0 = Tile{ letter: None, is_blank: true, value: 1 };
@@ -580,4 +1315,588 @@ mod tests {
         assert_ne!(c12, c22);
         assert_ne!(c11, c22);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_algebraic() {
+        assert_eq!(Coordinates::from_algebraic("H8", 15), Ok(Coordinates{ row: 7, column: 7 }));
+        assert_eq!(Coordinates::from_algebraic("a15", 15), Ok(Coordinates{ row: 14, column: 0 }));
+        for row in 0..15 {
+            for column in 0..15 {
+                let coordinates = Coordinates{ row, column };
+                assert_eq!(Coordinates::from_algebraic(&coordinates.to_algebraic().unwrap(), 15), Ok(coordinates));
+            }
+        }
+        assert_eq!(Coordinates{ row: 7, column: 7 }.to_algebraic(), Ok(String::from("H8")));
+        assert_eq!(Coordinates{ row: 0, column: 25 }.to_algebraic(), Ok(String::from("Z1")));
+        assert!(Coordinates{ row: 0, column: 26 }.to_algebraic().is_err());
+        assert!(Coordinates::from_algebraic("O1", 15).is_ok());
+        assert!(Coordinates::from_algebraic("P1", 15).is_err());
+        assert!(Coordinates::from_algebraic("I1", 8).is_err());
+        assert!(Coordinates::from_algebraic("H0", 15).is_err());
+        assert!(Coordinates::from_algebraic("H-1", 15).is_err());
+        assert!(Coordinates::from_algebraic("8H", 15).is_err());
+        assert!(Coordinates::from_algebraic("H", 15).is_err());
+        assert!(Coordinates::from_algebraic("", 15).is_err());
+        assert!(Coordinates::from_algebraic("É1", 15).is_err());
+        assert_eq!(Axis::from_name("Horizontal"), Some(Axis::Horizontal));
+        assert_eq!(Axis::from_name("down"), Some(Axis::Vertical));
+        assert_eq!(Axis::from_name("diagonal"), None);
+    }
+
+    #[test]
+    fn test_can_play_word_opening() {
+        let game_board = test_board(5, 5, &[]);
+        let dictionary = test_dictionary(&["CAT", "DOG"]);
+        let (start, axis, score) = game_board.can_play_word("cat", &test_rack("CAT"), &dictionary).unwrap();
+        assert_eq!(score, 3);
+        let delta = if axis == Axis::Horizontal { (0, 1) } else { (1, 0) };
+        let covered: Vec<Coordinates> = (0..3).map(|index|
+            Coordinates{ row: start.row + delta.0 * index, column: start.column + delta.1 * index }
+        ).collect();
+        assert!(covered.contains(&Coordinates{ row: 2, column: 2 }));
+        // A blank can stand in for a missing letter.
+        assert!(game_board.can_play_word("DOG", &test_rack("DO?"), &dictionary).is_some());
+        assert_eq!(game_board.can_play_word("DOG", &test_rack("CAT"), &dictionary), None);
+    }
+
+    #[test]
+    fn test_can_play_word_through_board_tiles() {
+        let game_board = test_board(5, 5, &[(2, 1, 'C'), (2, 2, 'A'), (2, 3, 'T')]);
+        let dictionary = test_dictionary(&["CAT", "CATS", "ACT"]);
+        assert_eq!(
+            game_board.can_play_word("CATS", &test_rack("S"), &dictionary),
+            Some((Coordinates{ row: 2, column: 1 }, Axis::Horizontal, 4))
+        );
+        // Only board tiles would be used.
+        assert_eq!(game_board.can_play_word("CAT", &test_rack("S"), &dictionary), None);
+        // Not enough tiles in the rack.
+        assert_eq!(game_board.can_play_word("ACT", &test_rack("S"), &dictionary), None);
+        // Not in the dictionary.
+        assert_eq!(game_board.can_play_word("CATZ", &test_rack("Z"), &dictionary), None);
+    }
+
+    #[test]
+    fn test_single_tile_primary_axis_horizontal() {
+        let game_board = test_board(5, 5, &[(2, 2, 'A'), (2, 3, 'T')]);
+        let played_tiles = vec![test_played_tile(2, 1, 'C')];
+        assert_eq!(game_board.primary_axis(&played_tiles), Axis::Horizontal);
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["CAT"]);
+        assert_eq!(game_board.score(&played_tiles), 3);
+    }
+
+    #[test]
+    fn test_single_tile_primary_axis_vertical() {
+        let game_board = test_board(5, 5, &[(2, 2, 'A'), (3, 2, 'T')]);
+        let played_tiles = vec![test_played_tile(1, 2, 'C')];
+        assert_eq!(game_board.primary_axis(&played_tiles), Axis::Vertical);
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["CAT"]);
+        assert_eq!(game_board.score(&played_tiles), 3);
+    }
+
+    #[test]
+    fn test_single_tile_primary_axis_both() {
+        let game_board = test_board(5, 5, &[(2, 0, 'A'), (0, 1, 'O'), (1, 1, 'N')]);
+        let played_tiles = vec![test_played_tile(2, 1, 'T')];
+        assert_eq!(game_board.primary_axis(&played_tiles), Axis::Horizontal);
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["AT", "ONT"]);
+        assert_eq!(game_board.score(&played_tiles), 5);
+    }
+
+    #[test]
+    fn test_score_includes_trailing_board_tiles() {
+        let game_board = test_board(5, 6, &[(2, 3, 'E'), (2, 4, 'R'), (2, 5, 'S')]);
+        let played_tiles = vec![test_played_tile(2, 0, 'C'), test_played_tile(2, 1, 'A'), test_played_tile(2, 2, 'T')];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["CATERS"]);
+        assert_eq!(game_board.score(&played_tiles), 6);
+        // A vertical play ending just above an existing tile.
+        let game_board = test_board(6, 5, &[(3, 2, 'S')]);
+        let played_tiles = vec![test_played_tile(0, 2, 'C'), test_played_tile(1, 2, 'A'), test_played_tile(2, 2, 'T')];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["CATS"]);
+        assert_eq!(game_board.score(&played_tiles), 4);
+    }
+
+    #[test]
+    fn test_scrambled_played_tiles() {
+        let game_board = GameBoardBuilder::new(7, 7)
+            .tile(1, 2, 'A', 1)
+            .tile(3, 1, 'O', 1)
+            .tile(3, 3, 'E', 1)
+            .modifier(0, 2, 2, 1)
+            .build();
+        let played_tiles = vec![test_played_tile(0, 2, 'C'), test_played_tile(2, 2, 'T'), test_played_tile(3, 2, 'S')];
+        let words = game_board.words_created(&played_tiles).unwrap();
+        let score = game_board.score(&played_tiles);
+        assert_eq!(words, vec!["CATS", "OSE"]);
+        assert_eq!(score, 8);
+        for scrambled in [[2, 0, 1], [1, 2, 0], [2, 1, 0]] {
+            let scrambled_tiles: Vec<PlayedTile> = scrambled.iter().map(|index| played_tiles[*index]).collect();
+            assert_eq!(game_board.words_created(&scrambled_tiles).unwrap(), words);
+            assert_eq!(game_board.score(&scrambled_tiles), score);
+            assert!(game_board.validate_play(&scrambled_tiles, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_letterless_board_tile_is_an_error() {
+        let mut game_board = test_board(5, 5, &[(2, 2, 'A')]);
+        game_board.board_tiles[2][2] = Some(Tile{ letter: None, is_blank: true, value: 0 });
+        let played_tiles = vec![test_played_tile(2, 3, 'T')];
+        let missing_letter = BoardError::MissingLetter { coordinates: Coordinates::new(2, 2) };
+        assert_eq!(game_board.words_created(&played_tiles), Err(missing_letter.clone()));
+        assert_eq!(missing_letter.to_string(), "The tile at (2, 2) has no letter.");
+        assert_eq!(game_board.validate_play(&played_tiles, false), Err(PlayError::UnassignedBlank));
+        // Words that do not reach the letterless tile are unaffected.
+        let played_tiles = vec![test_played_tile(0, 0, 'A'), test_played_tile(0, 1, 'T')];
+        assert_eq!(game_board.words_created(&played_tiles), Ok(vec![String::from("AT")]));
+    }
+
+    #[test]
+    fn test_unicode_letters() {
+        let game_state = |rack_letter: &str| serde_json::from_str::<GameSerializer>(&format!(
+            r#"{{"board_state": [{{"tile": {{"letter": "Ç", "is_blank": false, "value": 10}}, "row": 2, "column": 2}}],
+            "game_players": [], "board_layout": {{"rows": 5, "columns": 5, "modifiers": []}},
+            "turn_number": 1, "whose_turn_name": "AI", "num_tiles_remaining": 80,
+            "rack": [{{"tile": {{"letter": "{}", "is_blank": false, "value": 1}}, "count": 1}}],
+            "prev_move": null, "fetcher_player_id": 1}}"#,
+            rack_letter
+        )).unwrap();
+        let game_board = GameBoard::new(&game_state("É")).unwrap();
+        assert_eq!(game_board.get_tile(&Coordinates::new(2, 2)).unwrap().unwrap().get_letter(), Some('Ç'));
+        let rack = Rack::new(&game_state("É"));
+        assert_eq!(rack.tiles[0].get_letter(), Some('É'));
+        assert_eq!(rack.tiles[0].fill_blank('É').to_string(), "É");
+        assert_eq!(Tile{ letter: Some('É'), is_blank: true, value: 0 }.to_string(), "é");
+        let err = GameBoard::new(&game_state("CH")).err().unwrap();
+        assert_eq!(err, BoardError::UnsupportedLetter { letter: String::from("CH") });
+        assert_eq!(err.to_string(), "The tile letter CH is not a single character, which is not supported.");
+    }
+
+    #[test]
+    fn test_get_tile_bounds() {
+        let game_board = test_board(3, 4, &[(0, 0, 'A'), (2, 3, 'T')]);
+        assert_eq!(game_board.get_tile(&Coordinates::new(0, 0)).unwrap().unwrap().get_letter(), Some('A'));
+        assert_eq!(game_board.get_tile(&Coordinates::new(2, 3)).unwrap().unwrap().get_letter(), Some('T'));
+        assert_eq!(game_board.get_tile(&Coordinates::new(1, 1)), Some(&None));
+        for off_board in [(-1, 0), (0, -1), (3, 0), (0, 4), (-1, -1), (i32::MIN, 0), (0, i32::MAX)] {
+            assert_eq!(game_board.get_tile(&Coordinates::new(off_board.0, off_board.1)), None);
+        }
+    }
+
+    #[test]
+    fn test_is_occupied_bounds() {
+        let game_board = test_board(5, 7, &[(2, 3, 'A')]);
+        assert_eq!(game_board.is_occupied(&Coordinates{ row: 2, column: 3 }), Ok(true));
+        assert_eq!(game_board.is_occupied(&Coordinates{ row: 2, column: 4 }), Ok(false));
+        assert_eq!(
+            game_board.is_occupied(&Coordinates{ row: 5, column: 3 }),
+            Err(BoardError::RowOutOfBounds { row: 5, rows: 5 })
+        );
+        assert_eq!(
+            game_board.is_occupied(&Coordinates{ row: -1, column: 3 }),
+            Err(BoardError::RowOutOfBounds { row: -1, rows: 5 })
+        );
+        let column_err = game_board.is_occupied(&Coordinates{ row: 2, column: 7 }).unwrap_err();
+        assert_eq!(column_err, BoardError::ColumnOutOfBounds { column: 7, columns: 7 });
+        assert_eq!(column_err.to_string(), "Column 7 out of bounds for board with 7 columns.");
+    }
+
+    #[test]
+    fn test_leave_value() {
+        assert_eq!(leave_value(&[]), 0);
+        assert_eq!(leave_value(&test_rack("?S").tiles), LEAVE_BLANK_BONUS + LEAVE_S_BONUS);
+        // Balanced and distinct.
+        assert_eq!(leave_value(&test_rack("ERT").tiles), 0);
+        assert_eq!(leave_value(&test_rack("EET").tiles), -LEAVE_DUPLICATE_PENALTY);
+        assert_eq!(leave_value(&test_rack("QE").tiles), -LEAVE_Q_WITHOUT_U_PENALTY);
+        assert_eq!(leave_value(&test_rack("QUE").tiles), 0);
+        // Three more consonants than vowels.
+        assert_eq!(leave_value(&test_rack("BCDF").tiles), -3 * LEAVE_IMBALANCE_PENALTY);
+        assert!(leave_value(&test_rack("ERS").tiles) > leave_value(&test_rack("VVW").tiles));
+    }
+
+    #[test]
+    fn test_rack_balance() {
+        let tile = |letter: char, value: i32| Tile{ letter: Some(letter), is_blank: false, value };
+        assert_eq!(rack_balance(&[]), 1.0);
+        assert_eq!(rack_balance(&test_rack("??").tiles), 1.0);
+        // Two vowels in five tiles is balanced.
+        assert_eq!(rack_balance(&test_rack("AERST").tiles), 1.0);
+        assert_eq!(rack_balance(&test_rack("BCDF").tiles), 0.0);
+        assert!(rack_balance(&test_rack("AEIO").tiles) < rack_balance(&test_rack("BCDF").tiles));
+        assert_eq!(rack_balance(&[tile('A', 1), tile('E', 1), tile('K', 5), tile('K', 5), tile('R', 1)]), 0.0);
+        assert_eq!(rack_balance(&[tile('A', 1), tile('E', 1), tile('Q', 10), tile('R', 1), tile('T', 1)]), 0.0);
+        assert_eq!(rack_balance(&[tile('A', 1), tile('U', 1), tile('Q', 10), tile('R', 1), tile('T', 1)]), 1.0);
+    }
+
+    #[test]
+    fn test_score_opening_word() {
+        // The center square doubles the word.
+        let game_board = GameBoardBuilder::new(15, 15).modifier(7, 7, 1, 2).build();
+        let played_tiles = vec![
+            scoring_played_tile(7, 6, 'C'), scoring_played_tile(7, 7, 'A'), scoring_played_tile(7, 8, 'T')
+        ];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["CAT"]);
+        assert_eq!(game_board.score(&played_tiles), 10);
+    }
+
+    #[test]
+    fn test_score_crossing_word() {
+        // The modifier under a board tile has already been used, so only the played tiles get theirs.
+        let game_board = GameBoardBuilder::new(15, 15)
+            .modifier(7, 7, 1, 2)
+            .word(7, 6, Axis::Horizontal, "CAT")
+            .build();
+        let played_tiles = vec![scoring_played_tile(6, 7, 'H'), scoring_played_tile(8, 7, 'T')];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["HAT"]);
+        assert_eq!(game_board.score(&played_tiles), 6);
+    }
+
+    #[test]
+    fn test_score_letter_and_word_multipliers() {
+        let game_board = GameBoardBuilder::new(15, 15)
+            .word(7, 6, Axis::Horizontal, "CAT")
+            .modifier(8, 7, 2, 1)
+            .modifier(9, 7, 1, 3)
+            .build();
+        let played_tiles = vec![scoring_played_tile(8, 7, 'X'), scoring_played_tile(9, 7, 'E')];
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["AXE"]);
+        // (1 + 8 * 2 + 1) * 3
+        assert_eq!(game_board.score(&played_tiles), 54);
+    }
+
+    #[test]
+    fn test_score_bingo() {
+        let game_board = GameBoardBuilder::new(15, 15).modifier(7, 7, 1, 2).build();
+        let played_tiles: Vec<PlayedTile> = "RETAINS".chars().enumerate()
+            .map(|(index, letter)| scoring_played_tile(7, 4 + index as i32, letter))
+            .collect();
+        assert_eq!(game_board.words_created(&played_tiles).unwrap(), vec!["RETAINS"]);
+        assert_eq!(game_board.score(&played_tiles), 7 * 2 + 50);
+    }
+
+    #[test]
+    fn test_even_dimension_center() {
+        let game_board = test_board(4, 6, &[]);
+        let centers = vec![Coordinates::new(1, 2), Coordinates::new(1, 3), Coordinates::new(2, 2), Coordinates::new(2, 3)];
+        assert_eq!(game_board.anchors(), centers);
+        let opening = vec![test_played_tile(1, 3, 'A'), test_played_tile(1, 4, 'T')];
+        assert!(game_board.validate_play(&opening, true).is_ok());
+        let off_center = vec![test_played_tile(0, 3, 'A'), test_played_tile(0, 4, 'T')];
+        assert_eq!(game_board.validate_play(&off_center, true), Err(PlayError::NotOnStartSquare));
+        // Only the columns are even.
+        assert_eq!(test_board(5, 4, &[]).anchors(), vec![Coordinates::new(2, 1), Coordinates::new(2, 2)]);
+        assert_eq!(test_board(5, 5, &[]).anchors(), vec![Coordinates::new(2, 2)]);
+    }
+
+    #[test]
+    fn test_layout_start_square() {
+        let game_state = |start_square: &str| serde_json::from_str::<GameSerializer>(&format!(
+            r#"{{"board_state": [], "game_players": [],
+            "board_layout": {{"rows": 6, "columns": 6, "modifiers": [], {}}},
+            "turn_number": 0, "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [],
+            "prev_move": null, "fetcher_player_id": 1}}"#,
+            start_square
+        )).unwrap();
+        let game_board = GameBoard::new(&game_state(r#""start_square": {"row": 0, "column": 1}"#)).unwrap();
+        assert_eq!(game_board.anchors(), vec![Coordinates::new(0, 1)]);
+        let opening = vec![test_played_tile(0, 0, 'A'), test_played_tile(0, 1, 'T')];
+        assert!(game_board.validate_play(&opening, true).is_ok());
+        assert_eq!(
+            GameBoard::new(&game_state(r#""start_square": {"row": 6, "column": 1}"#)).err(),
+            Some(BoardError::RowOutOfBounds { row: 6, rows: 6 })
+        );
+        // Without a start square the even board has four center squares.
+        assert_eq!(GameBoard::new(&game_state(r#""num_tiles": 7"#)).unwrap().anchors().len(), 4);
+    }
+
+    #[test]
+    fn test_modifier_start_square() {
+        let game_state: GameSerializer = serde_json::from_str(
+            r#"{"board_state": [], "game_players": [],
+            "board_layout": {"rows": 7, "columns": 7, "modifiers": [
+                {"row": 0, "column": 0, "modifier": {"word_multiplier": 3, "letter_multiplier": 1}},
+                {"row": 5, "column": 2, "modifier": {"word_multiplier": 2, "letter_multiplier": 1}, "is_start": true}
+            ]},
+            "turn_number": 0, "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [],
+            "prev_move": null, "fetcher_player_id": 1}"#
+        ).unwrap();
+        let game_board = GameBoard::new(&game_state).unwrap();
+        assert_eq!(game_board.anchors(), vec![Coordinates::new(5, 2)]);
+        let opening = vec![test_played_tile(5, 2, 'A'), test_played_tile(5, 3, 'T')];
+        assert!(game_board.covers_start_square(&opening));
+        assert!(game_board.validate_play(&opening, true).is_ok());
+        // The center is not a start square on this layout.
+        let centered = vec![test_played_tile(3, 3, 'A'), test_played_tile(3, 4, 'T')];
+        assert!(!game_board.covers_start_square(&centered));
+        assert_eq!(game_board.validate_play(&centered, true), Err(PlayError::NotOnStartSquare));
+        assert_eq!(PlayError::NotOnStartSquare.to_string(), "The opening play does not cover the start square.");
+    }
+
+    #[test]
+    fn test_score_through_unused_marked_start_square() {
+        let tile = |letter: &str, row: i32, column: i32| format!(
+            r#"{{"tile": {{"letter": "{}", "is_blank": false, "value": 1}}, "row": {}, "column": {}}}"#,
+            letter, row, column
+        );
+        let start = |row: i32, column: i32| format!(
+            r#"{{"row": {}, "column": {}, "modifier": {{"word_multiplier": 2, "letter_multiplier": 1}}, "is_start": true}}"#,
+            row, column
+        );
+        let game_state: GameSerializer = serde_json::from_str(&format!(
+            r#"{{"board_state": [{}, {}], "game_players": [],
+            "board_layout": {{"rows": 7, "columns": 7, "modifiers": [{}, {}]}},
+            "turn_number": 1, "whose_turn_name": "AI", "num_tiles_remaining": 80, "rack": [],
+            "prev_move": null, "fetcher_player_id": 1}}"#,
+            tile("A", 3, 2), tile("T", 3, 3), start(3, 2), start(3, 4)
+        )).unwrap();
+        let game_board = GameBoard::new(&game_state).unwrap();
+        // The play covers the start square that the opening did not.
+        let played_tiles = [test_played_tile(2, 4, 'X'), test_played_tile(3, 4, 'Y')];
+        assert!(game_board.covers_start_square(&played_tiles));
+        assert!(game_board.validate_play(&played_tiles.to_vec(), false).is_ok());
+        assert_eq!(game_board.words_and_score(&played_tiles), Ok((vec![String::from("XY"), String::from("ATY")], 10)));
+    }
+
+    #[test]
+    fn test_score_uses_layout_bingo() {
+        let played_tiles = vec![test_played_tile(2, 1, 'C'), test_played_tile(2, 2, 'A'), test_played_tile(2, 3, 'T')];
+        assert_eq!(test_board(5, 5, &[]).score(&played_tiles), 3);
+        let game_board = GameBoardBuilder::new(5, 5).bingo(20, 3).build();
+        assert_eq!(game_board.score(&played_tiles), 23);
+    }
+
+    #[test]
+    fn test_score_through_unused_start_square() {
+        // An even board has four start squares, and the opening play covered only two of them.
+        let game_board = test_board(4, 4, &[(1, 1, 'A'), (1, 2, 'T')]);
+        let played_tiles = [test_played_tile(2, 1, 'X'), test_played_tile(2, 2, 'Y')];
+        assert!(game_board.covers_start_square(&played_tiles));
+        assert_eq!(game_board.words_and_score(&played_tiles), Ok((vec![
+            String::from("XY"), String::from("AX"), String::from("TY")
+        ], 6)));
+        assert_eq!(game_board.score(&played_tiles), 6);
+    }
+
+    #[test]
+    fn test_validate_play() {
+        let game_board = test_board(5, 5, &[(2, 2, 'A')]);
+        assert!(game_board.validate_play(&vec![test_played_tile(2, 3, 'T')], false).is_ok());
+        assert_eq!(game_board.validate_play(&Vec::new(), false), Err(PlayError::NoTiles));
+        assert_eq!(game_board.validate_play(&vec![test_played_tile(2, 2, 'T')], false), Err(PlayError::Unavailable));
+        assert_eq!(game_board.validate_play(&vec![test_played_tile(5, 2, 'T')], false), Err(PlayError::Unavailable));
+        assert_eq!(
+            game_board.validate_play(&vec![test_played_tile(2, 3, 'T'), test_played_tile(3, 4, 'A')], false),
+            Err(PlayError::NotInLine)
+        );
+        // The board tile fills the gap between the played tiles, but nothing fills the second gap.
+        assert_eq!(
+            game_board.validate_play(&vec![test_played_tile(2, 1, 'T'), test_played_tile(2, 3, 'T')], false),
+            Ok((vec![String::from("TAT")], 3))
+        );
+        assert_eq!(
+            game_board.validate_play(&vec![test_played_tile(2, 1, 'T'), test_played_tile(2, 4, 'T')], false),
+            Err(PlayError::NotContinuous)
+        );
+        // A lone tile away from the board tiles forms only a one letter word.
+        assert_eq!(game_board.validate_play(&vec![test_played_tile(0, 0, 'A')], false), Err(PlayError::NotConnected));
+
+        let empty_board = test_board(5, 5, &[]);
+        let opening = vec![test_played_tile(2, 2, 'A'), test_played_tile(2, 3, 'T')];
+        assert!(empty_board.validate_play(&opening, true).is_ok());
+        assert_eq!(
+            empty_board.validate_play(&vec![test_played_tile(2, 2, 'A')], true),
+            Err(PlayError::TooFewOpeningTiles)
+        );
+        assert_eq!(
+            empty_board.validate_play(&vec![test_played_tile(2, 3, 'A'), test_played_tile(2, 4, 'T')], true),
+            Err(PlayError::NotOnStartSquare)
+        );
+        assert_eq!(
+            PlayError::NotConnected.to_string(), "The tiles do not touch any board tile."
+        );
+    }
+
+    #[test]
+    fn test_is_connected() {
+        let game_board = test_board(5, 5, &[(2, 2, 'A')]);
+        assert!(game_board.is_connected(&[test_played_tile(2, 3, 'T')]));
+        assert!(game_board.is_connected(&[test_played_tile(0, 1, 'C'), test_played_tile(1, 1, 'A'), test_played_tile(2, 1, 'T')]));
+        // Diagonal neighbours do not count.
+        assert!(!game_board.is_connected(&[test_played_tile(1, 1, 'A'), test_played_tile(1, 0, 'T')]));
+        assert!(!game_board.is_connected(&[test_played_tile(4, 4, 'A')]));
+        // Tiles on the edges have neighbours at negative and past the end coordinates.
+        assert!(!game_board.is_connected(&[test_played_tile(0, 0, 'A'), test_played_tile(0, 1, 'T')]));
+        assert!(!game_board.is_connected(&[test_played_tile(4, 4, 'A'), test_played_tile(3, 4, 'T')]));
+        let corner_board = test_board(3, 3, &[(0, 0, 'A')]);
+        assert!(corner_board.is_connected(&[test_played_tile(0, 1, 'T')]));
+        assert!(corner_board.is_connected(&[test_played_tile(1, 0, 'T')]));
+    }
+
+    #[test]
+    fn test_is_available() {
+        let game_board = test_board(5, 5, &[(2, 2, 'A')]);
+        assert!(game_board.is_available(&[test_played_tile(2, 3, 'T'), test_played_tile(2, 4, 'S')]));
+        assert!(!game_board.is_available(&[test_played_tile(2, 1, 'T'), test_played_tile(2, 2, 'A')]));
+        assert!(!game_board.is_available(&[test_played_tile(-1, 2, 'T')]));
+        assert!(!game_board.is_available(&[test_played_tile(2, -1, 'T')]));
+        assert!(!game_board.is_available(&[test_played_tile(5, 2, 'T')]));
+        assert!(!game_board.is_available(&[test_played_tile(2, 5, 'T')]));
+    }
+
+    #[test]
+    fn test_covers_start_square() {
+        let game_board = test_board(5, 5, &[]);
+        assert!(game_board.covers_start_square(&[test_played_tile(2, 1, 'A'), test_played_tile(2, 2, 'T')]));
+        assert!(game_board.covers_start_square(&[test_played_tile(2, 2, 'A'), test_played_tile(3, 2, 'T')]));
+        assert!(!game_board.covers_start_square(&[test_played_tile(1, 1, 'A'), test_played_tile(1, 2, 'T')]));
+        assert!(!game_board.covers_start_square(&[]));
+    }
+
+    #[test]
+    fn test_is_continuous() {
+        let game_board = test_board(8, 5, &[(1, 2, 'C'), (3, 2, 'T'), (4, 2, 'S')]);
+        // Board tiles fill the interior gaps of a vertical play.
+        let played_tiles = vec![test_played_tile(0, 2, 'S'), test_played_tile(2, 2, 'A'), test_played_tile(5, 2, 'H')];
+        assert!(game_board.is_continuous(&played_tiles));
+        // The order of the played tiles does not matter.
+        let unsorted = vec![played_tiles[2], played_tiles[0], played_tiles[1]];
+        assert!(game_board.is_continuous(&unsorted));
+        // Nothing fills the gap at row 6.
+        let played_tiles = vec![test_played_tile(7, 2, 'S'), test_played_tile(5, 2, 'H'), test_played_tile(2, 2, 'A')];
+        assert!(!game_board.is_continuous(&played_tiles));
+        let played_tiles = vec![test_played_tile(5, 2, 'S'), test_played_tile(0, 2, 'A')];
+        assert!(!game_board.is_continuous(&played_tiles));
+        assert!(game_board.is_continuous(&vec![test_played_tile(6, 0, 'A')]));
+        assert!(!game_board.is_continuous(&vec![test_played_tile(0, 0, 'A'), test_played_tile(1, 1, 'A')]));
+        // A horizontal play whose tiles are given in reverse order.
+        let game_board = test_board(5, 5, &[(2, 2, 'A')]);
+        assert!(game_board.is_continuous(&vec![test_played_tile(2, 3, 'T'), test_played_tile(2, 1, 'C')]));
+    }
+
+    #[test]
+    fn test_unseen_tiles() {
+        let tile = |letter: char| Tile{ letter: Some(letter), is_blank: false, value: 1 };
+        let blank = Tile{ letter: None, is_blank: true, value: 0 };
+        let tile_bag = TileBag{ tiles: vec![tile('A'), tile('A'), tile('A'), tile('B'), tile('C'), blank, blank] };
+        let mut game_board = test_board(3, 3, &[(1, 0, 'A'), (1, 1, 'B')]);
+        game_board.board_tiles[1][2] = Some(Tile{ letter: Some('Z'), is_blank: true, value: 0 });
+        let unseen_tiles = tile_bag.unseen_tiles(&game_board, &test_rack("A?"));
+        assert_eq!(unseen_tiles, vec![tile('A'), tile('C')]);
+    }
+
+    #[test]
+    fn test_exposed_premium_value() {
+        let game_board = GameBoardBuilder::new(5, 5)
+            .tile(2, 2, 'A', 1)
+            .modifier(1, 3, 1, 3)
+            .modifier(3, 2, 2, 1)
+            .modifier(0, 0, 1, 2)
+            .build();
+        // AT across opens the triple word square above the T.
+        let risky = vec![test_played_tile(2, 3, 'T')];
+        assert_eq!(game_board.exposed_premium_value(&risky), 2 * EXPOSED_WORD_MULTIPLIER_PENALTY);
+        // TA across opens no premium squares.
+        let defensive = vec![test_played_tile(2, 1, 'T')];
+        assert_eq!(game_board.exposed_premium_value(&defensive), 0);
+        // TAT down covers the double letter square and opens the triple word square beside the top T.
+        let down = vec![test_played_tile(1, 2, 'T'), test_played_tile(3, 2, 'T')];
+        assert_eq!(game_board.exposed_premium_value(&down), 2 * EXPOSED_WORD_MULTIPLIER_PENALTY);
+        assert_eq!(game_board.open_neighbour_count(&down), 6);
+    }
+
+    #[test]
+    fn test_cross_checks() {
+        let dictionary = test_dictionary(&["CAT", "CATS", "AT", "TA", "SCAT"]);
+        // C A T down the first column, with T alone in the third column.
+        let game_board = test_board(5, 4, &[(1, 0, 'C'), (2, 0, 'A'), (3, 0, 'T'), (2, 2, 'T')]);
+        let cross_checks = game_board.cross_checks(&dictionary, &Axis::Horizontal);
+        // Above and below CAT only S can extend it, forming SCAT or CATS.
+        assert!(cross_checks.allows(&Coordinates::new(0, 0), 'S'));
+        assert!(!cross_checks.allows(&Coordinates::new(0, 0), 'A'));
+        assert!(cross_checks.allows(&Coordinates::new(4, 0), 'S'));
+        assert!(!cross_checks.allows(&Coordinates::new(4, 0), 'A'));
+        // Above and below T, which must form AT or TA.
+        assert!(cross_checks.allows(&Coordinates::new(1, 2), 'A'));
+        assert!(cross_checks.allows(&Coordinates::new(3, 2), 'A'));
+        assert!(!cross_checks.allows(&Coordinates::new(3, 2), 'T'));
+        // Squares with no tiles above or below allow any letter.
+        assert!(cross_checks.allows(&Coordinates::new(2, 1), 'Z'));
+        // Playing down the column, the squares beside the tiles are checked across the rows.
+        let cross_checks = game_board.cross_checks(&dictionary, &Axis::Vertical);
+        assert!(cross_checks.allows(&Coordinates::new(3, 1), 'A'));
+        assert!(!cross_checks.allows(&Coordinates::new(3, 1), 'S'));
+        // Between A and T no letter forms a word.
+        assert!(!cross_checks.allows(&Coordinates::new(2, 1), 'A'));
+        assert!(cross_checks.allows(&Coordinates::new(0, 0), 'Z'));
+    }
+
+    #[test]
+    fn test_board_and_rack_display() {
+        let mut game_board = test_board(3, 4, &[(1, 1, 'A')]);
+        game_board.board_tiles[1][2] = Some(Tile{ letter: Some('T'), is_blank: true, value: 0 });
+        let modifiers = &mut Arc::make_mut(&mut game_board.layout).modifiers;
+        modifiers[0][0] = Modifier{ letter_multiplier: 1, word_multiplier: 3 };
+        modifiers[2][3] = Modifier{ letter_multiplier: 2, word_multiplier: 1 };
+        // A tile covers the modifier under it.
+        modifiers[1][1] = Modifier{ letter_multiplier: 3, word_multiplier: 1 };
+        assert_eq!(
+            game_board.to_string(),
+            "    A  B  C  D\n 1 3W  .  .  .\n 2  .  A  t  .\n 3  .  .  . 2L"
+        );
+        assert_eq!(test_rack("CAT?").to_string(), "C A T ?");
+    }
+
+    #[test]
+    fn test_build_played_tiles() {
+        let game_board = test_board(3, 4, &[(0, 1, 'A')]);
+        let tile = Tile{ letter: Some('T'), is_blank: false, value: 1 };
+        let played_tiles = game_board.build_played_tiles(&Coordinates::new(0, 0), vec![&tile, &tile], &Axis::Horizontal);
+        assert_eq!(played_tiles, Ok(vec![test_played_tile(0, 0, 'T'), test_played_tile(0, 2, 'T')]));
+        assert_eq!(
+            game_board.build_played_tiles(&Coordinates::new(0, 1), vec![&tile], &Axis::Vertical),
+            Err(BoardError::StartOccupied { start: Coordinates::new(0, 1) })
+        );
+        let err = game_board.build_played_tiles(&Coordinates::new(0, 0), vec![&tile; 4], &Axis::Horizontal)
+            .unwrap_err();
+        assert_eq!(err, BoardError::InsufficientSpace { tiles: 4, axis: Axis::Horizontal, start: Coordinates::new(0, 0) });
+        assert_eq!(err.to_string(), "Not enough space on the board to play 4 tiles on the Horizontal axis from (0, 0).");
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let game_board = GameBoardBuilder::new(5, 5).tile(2, 2, 'A', 1).modifier(2, 3, 1, 3).build();
+        let c = Tile{ letter: Some('C'), is_blank: false, value: 3 };
+        let t = Tile{ letter: Some('T'), is_blank: false, value: 1 };
+        // C and T are played either side of the A, and T covers the triple word square.
+        assert_eq!(
+            game_board.evaluate(Coordinates::new(2, 1), &[c, t], Axis::Horizontal),
+            Ok((vec![String::from("CAT")], 15))
+        );
+        assert_eq!(
+            game_board.evaluate(Coordinates::new(1, 2), &[t], Axis::Vertical),
+            Ok((vec![String::from("TA")], 2))
+        );
+        assert_eq!(
+            game_board.evaluate(Coordinates::new(2, 2), &[t], Axis::Vertical),
+            Err(BoardError::StartOccupied { start: Coordinates::new(2, 2) })
+        );
+        assert_eq!(
+            game_board.evaluate(Coordinates::new(0, 0), &[c, t], Axis::Horizontal),
+            Err(BoardError::IllegalPlay { reason: PlayError::NotConnected })
+        );
+        let blank = Tile{ letter: None, is_blank: true, value: 0 };
+        let err = game_board.evaluate(Coordinates::new(1, 2), &[blank], Axis::Vertical).unwrap_err();
+        assert_eq!(err, BoardError::IllegalPlay { reason: PlayError::UnassignedBlank });
+        assert_eq!(err.to_string(), "Illegal play: A blank tile has no letter assigned.");
+        assert_eq!(
+            game_board.evaluate(Coordinates::new(1, 2), &[blank.fill_blank('T')], Axis::Vertical),
+            Ok((vec![String::from("TA")], 1))
+        );
+    }
+
+    #[test]
+    fn test_axis_display() {
+        assert_eq!(Axis::Horizontal.to_string(), "Horizontal");
+        assert_eq!(Axis::Vertical.to_string(), "Vertical");
+    }
+}
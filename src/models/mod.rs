@@ -1,3 +1,4 @@
 pub mod game_models;
 pub mod serializers;
 pub mod config_models;
+pub mod record_models;
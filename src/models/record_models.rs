@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// The AI player's results across all completed games that it has seen.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GameRecord {
+    wins: u32,
+    losses: u32,
+    ties: u32,
+    total_score: i64,
+    total_margin: i64,
+    recorded_game_ids: HashSet<i32>,
+}
+
+impl GameRecord {
+    /// Load a record from a state file, starting a new record if the file does not exist.
+    pub fn load(path: &str) -> Result<GameRecord, String> {
+        match fs::read_to_string(path) {
+            Ok(record_string) => serde_json::from_str(&record_string)
+                .map_err(|e| format!("Failed to parse game record {}: {}", path, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(GameRecord::default()),
+            Err(e) => Err(format!("Failed to read game record {}: {}", path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let record_string = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize game record: {}", e))?;
+        fs::write(path, record_string)
+            .map_err(|e| format!("Failed to write game record {}: {}", path, e))
+    }
+
+    pub fn is_recorded(&self, game_id: i32) -> bool {
+        self.recorded_game_ids.contains(&game_id)
+    }
+
+    /// Add the result of a completed game to the record.
+    ///
+    /// The margin is taken against the best opponent. Return false without changing the
+    /// record if the game has already been recorded.
+    pub fn record(&mut self, game_id: i32, ai_score: i32, opponent_scores: &[i32]) -> bool {
+        if !self.recorded_game_ids.insert(game_id) {
+            return false;
+        }
+        let best_opponent_score = opponent_scores.iter().max().copied().unwrap_or(0);
+        match ai_score.cmp(&best_opponent_score) {
+            Ordering::Greater => self.wins += 1,
+            Ordering::Less => self.losses += 1,
+            Ordering::Equal => self.ties += 1,
+        }
+        self.total_score += ai_score as i64;
+        self.total_margin += (ai_score - best_opponent_score) as i64;
+        true
+    }
+
+    pub fn games_played(&self) -> u32 {
+        self.wins + self.losses + self.ties
+    }
+
+    pub fn average_score(&self) -> f64 {
+        if self.games_played() == 0 {
+            return 0.0;
+        }
+        self.total_score as f64 / self.games_played() as f64
+    }
+
+    pub fn average_margin(&self) -> f64 {
+        if self.games_played() == 0 {
+            return 0.0;
+        }
+        self.total_margin as f64 / self.games_played() as f64
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "{} wins, {} losses, {} ties. Average score {:.1}, average margin {:.1}.",
+            self.wins, self.losses, self.ties, self.average_score(), self.average_margin()
+        )
+    }
+}
+
+/// The word and score of one turn the AI player played in a game. The word is None for an
+/// exchange or a pass.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TurnRecord {
+    pub game_id: String,
+    pub turn_number: i32,
+    pub word: Option<String>,
+    pub score: i32,
+}
+
+impl TurnRecord {
+    /// Append the turn to a JSON lines file, creating the file if it does not exist.
+    pub fn append(&self, path: &str) -> Result<(), String> {
+        let turn_string = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize turn record: {}", e))?;
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("Failed to open score history {}: {}", path, e))?;
+        writeln!(file, "{}", turn_string)
+            .map_err(|e| format!("Failed to write score history {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::tests::TempPath;
+
+    #[test]
+    fn test_record_aggregate() {
+        let mut record = GameRecord::default();
+        assert!(record.record(1, 300, &[250]));
+        assert!(record.record(2, 200, &[260, 180]));
+        assert!(record.record(3, 310, &[310]));
+        assert!(record.record(4, 410, &[320]));
+        // Games are only counted once.
+        assert!(!record.record(4, 410, &[320]));
+        assert_eq!(record.wins, 2);
+        assert_eq!(record.losses, 1);
+        assert_eq!(record.ties, 1);
+        assert_eq!(record.games_played(), 4);
+        assert_eq!(record.average_score(), 305.0);
+        assert_eq!(record.average_margin(), 20.0);
+        assert!(record.is_recorded(3));
+        assert!(!record.is_recorded(5));
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let temp_path = TempPath::new("record.json");
+        let path = temp_path.as_str();
+        let mut record = GameRecord::load(path).unwrap();
+        assert_eq!(record.games_played(), 0);
+        record.record(7, 120, &[100]);
+        record.save(path).unwrap();
+        let loaded = GameRecord::load(path).unwrap();
+        assert_eq!(loaded.wins, 1);
+        assert_eq!(loaded.average_margin(), 20.0);
+        assert!(loaded.is_recorded(7));
+    }
+
+    #[test]
+    fn test_turn_record_append() {
+        let temp_path = TempPath::new("history.jsonl");
+        let path = temp_path.as_str();
+        let first = TurnRecord{ game_id: String::from("3"), turn_number: 0, word: Some(String::from("CAT")), score: 10 };
+        let second = TurnRecord{ game_id: String::from("3"), turn_number: 2, word: None, score: 0 };
+        first.append(path).unwrap();
+        second.append(path).unwrap();
+        let turns: Vec<TurnRecord> = fs::read_to_string(path).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(turns, vec![first, second]);
+    }
+}
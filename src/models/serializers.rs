@@ -3,15 +3,23 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::Flexible, TimestampSeconds};
 
+const DEFAULT_BINGO_BONUS: i32 = 50;
+const DEFAULT_NUM_TILES: i32 = 7;
+
 #[derive(Debug)]
 #[serde_with::serde_as]
 #[derive(Deserialize)]
 pub struct GameInfo {
+    // The server sends string seconds, but numeric seconds are accepted too in case that changes.
     #[serde_as(as = "TimestampSeconds<String, Flexible>")]
     pub started: DateTime<Utc>,
     #[serde_as(as = "Option<TimestampSeconds<String, Flexible>>")]
     pub completed: Option<DateTime<Utc>>,
     pub whose_turn_name: String,
+    #[serde(default)]
+    pub whose_turn_id: Option<i32>,
+    #[serde(default)]
+    pub turn_number: Option<i32>,
     pub game_players: Vec<GamePlayerInfo>,
     pub id: i32,
 }
@@ -30,7 +38,7 @@ pub struct PlayerInfo {
     pub display_name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct GameSerializer {
     pub board_state: Vec<PlayedTileSerializer>,
     pub game_players: Vec<GamePlayerSerializer>,
@@ -41,6 +49,11 @@ pub struct GameSerializer {
     pub rack: Vec<TileCountSerializer>,
     pub prev_move: Option<PrevMoveSerializer>,
     pub fetcher_player_id: i32,
+    #[serde(default)]
+    pub tile_distribution: Vec<TileCountSerializer>,
+    /// The id of the word list the game is played with, if the server names one.
+    #[serde(default)]
+    pub dictionary: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -53,27 +66,27 @@ pub struct FlatPlayedTileSerializer {
     pub letter: Option<char>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct PlayedTileSerializer {
     pub tile: TileSerializer,
     pub row: i32,
     pub column: i32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TileSerializer {
     pub letter: Option<String>,
     pub is_blank: bool,
     pub value: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct TileCountSerializer {
     pub tile: TileSerializer,
     pub count: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct GamePlayerSerializer {
     pub score: i32,
     pub turn_order: i32,
@@ -81,38 +94,122 @@ pub struct GamePlayerSerializer {
     pub num_tiles_remaining: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct PlayerSerializer {
     pub id: i32,
     pub display_name: String,
 }
 
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct BoardLayoutSerializer {
     pub rows: i32,
     pub columns: i32,
     pub modifiers: Vec<PositionedModifierSerializer>,
+    #[serde(default = "default_bingo_bonus")]
+    pub bingo_bonus: i32,
+    #[serde(default = "default_num_tiles")]
+    pub num_tiles: i32,
+    /// The square the opening play must cover, if the layout does not use the center.
+    #[serde(default)]
+    pub start_square: Option<SquareSerializer>,
 }
 
-#[derive(Deserialize)]
+fn default_bingo_bonus() -> i32 {
+    DEFAULT_BINGO_BONUS
+}
+
+fn default_num_tiles() -> i32 {
+    DEFAULT_NUM_TILES
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SquareSerializer {
+    pub row: i32,
+    pub column: i32,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct PositionedModifierSerializer {
     pub row: i32,
     pub column: i32,
     pub modifier: ModifierSerializer,
+    /// True iff the opening play must cover this square.
+    #[serde(default)]
+    pub is_start: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ModifierSerializer {
     pub word_multiplier: i32,
     pub letter_multiplier: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct PrevMoveSerializer {
     pub word: Option<String>,
     pub score: i32,
     pub player_id: i32,
     pub display_name: String,
     pub exchanged_count: i32,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_layout_bingo_defaults() {
+        let layout: BoardLayoutSerializer = serde_json::from_str(r#"{"rows": 15, "columns": 15, "modifiers": []}"#)
+            .unwrap();
+        assert_eq!(layout.bingo_bonus, 50);
+        assert_eq!(layout.num_tiles, 7);
+        let layout: BoardLayoutSerializer = serde_json::from_str(
+            r#"{"rows": 9, "columns": 9, "modifiers": [], "bingo_bonus": 30, "num_tiles": 5}"#
+        ).unwrap();
+        assert_eq!(layout.bingo_bonus, 30);
+        assert_eq!(layout.num_tiles, 5);
+        assert!(layout.start_square.is_none());
+    }
+
+    #[test]
+    fn test_game_state_round_trip() {
+        let game_string = r#"{"board_state": [{"tile": {"letter": "A", "is_blank": false, "value": 1}, "row": 7, "column": 7}],
+            "game_players": [{"score": 2, "turn_order": 0, "player": {"id": 1, "display_name": "AI"}, "num_tiles_remaining": 7}],
+            "board_layout": {"rows": 15, "columns": 15, "modifiers": [
+                {"row": 0, "column": 0, "modifier": {"word_multiplier": 3, "letter_multiplier": 1}}
+            ]},
+            "turn_number": 1, "whose_turn_name": "AI", "num_tiles_remaining": 80,
+            "rack": [{"tile": {"letter": null, "is_blank": true, "value": 0}, "count": 2}],
+            "prev_move": {"word": "A", "score": 2, "player_id": 1, "display_name": "AI", "exchanged_count": 0},
+            "fetcher_player_id": 1}"#;
+        let game_state: GameSerializer = serde_json::from_str(game_string).unwrap();
+        let snapshot = serde_json::to_string(&game_state).unwrap();
+        let restored: GameSerializer = serde_json::from_str(&snapshot).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), snapshot);
+        assert_eq!(restored.board_state[0].tile.letter.as_deref(), Some("A"));
+        assert_eq!(restored.game_players[0].player.display_name, "AI");
+        assert_eq!(restored.board_layout.modifiers[0].modifier.word_multiplier, 3);
+        assert_eq!(restored.board_layout.bingo_bonus, 50);
+        assert_eq!(restored.rack[0].count, 2);
+        assert_eq!(restored.prev_move.unwrap().word.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn test_game_info_timestamp_formats() {
+        let players = r#""whose_turn_name": "AI", "game_players": [], "id": 3"#;
+        for (started, completed) in [
+            (r#""1650000000""#, r#""1650003600.5""#),
+            ("1650000000", "1650003600.5"),
+            ("1650000000.0", "1650003600"),
+        ] {
+            let game_string = format!(r#"{{"started": {}, "completed": {}, {}}}"#, started, completed, players);
+            let game_info: GameInfo = serde_json::from_str(&game_string).unwrap();
+            assert_eq!(game_info.started.timestamp(), 1650000000);
+            assert_eq!(game_info.completed.unwrap().timestamp(), 1650003600);
+        }
+        let game_string = format!(r#"{{"started": 1650000000, "completed": null, {}}}"#, players);
+        let game_info: GameInfo = serde_json::from_str(&game_string).unwrap();
+        assert!(game_info.completed.is_none());
+    }
+}
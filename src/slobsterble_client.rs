@@ -1,167 +1,693 @@
-use log;
 use reqwest::header::{AUTHORIZATION};
+use reqwest::StatusCode;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
 
 use crate::models::serializers::{FlatPlayedTileSerializer, GameInfo, GameSerializer};
 use crate::models::config_models::{Config, Token, TokenPair};
 
+/// The response header with which a server marks a games list that it held until the list
+/// changed or the requested wait ended.
+const LONG_POLL_HEADER: &str = "x-long-poll";
+
+/// How long to wait before logging in again after the server rejects the credentials.
+const REJECTED_CREDENTIALS_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+pub enum AuthError {
+    /// The server rejected the username or password, so logging in again will fail too.
+    CredentialsRejected { status: StatusCode },
+    /// The login failed in a way that may be temporary, such as a network error.
+    Transient(reqwest::Error),
+}
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AuthError::CredentialsRejected { status } =>
+                write!(f, "Login credentials were rejected with status {}. Check the username and password.", status),
+            AuthError::Transient(err) => write!(f, "Login failed: {}", err),
+        }
+    }
+}
+impl Error for AuthError {}
+impl From<reqwest::Error> for AuthError {
+    fn from(err: reqwest::Error) -> AuthError {
+        match err.status() {
+            Some(status) if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN =>
+                AuthError::CredentialsRejected { status },
+            _ => AuthError::Transient(err),
+        }
+    }
+}
+
+/// A failure to play a turn.
+#[derive(Debug)]
+pub enum TurnError {
+    /// The server refused the play, giving the reason in the response body.
+    Rejected { status: StatusCode, reason: String },
+    /// The request failed without the server judging the play.
+    Request(reqwest::Error),
+}
+impl TurnError {
+
+    /// Return true iff the play failed in a way that may be temporary, such as a timeout or a
+    /// server error, so that it is worth trying again once it is known not to have been made.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TurnError::Rejected { .. } => false,
+            TurnError::Request(err) => is_retryable(err, true),
+        }
+    }
+}
+impl fmt::Display for TurnError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TurnError::Rejected { status, reason } => write!(f, "Play rejected with status {}: {}", status, reason),
+            TurnError::Request(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl Error for TurnError {}
 
 #[derive(Debug)]
 pub struct SlobsterbleClient {
-    client: reqwest::blocking::Client,
-    tokens: TokenPair,
+    client: reqwest::Client,
+    tokens: Mutex<TokenPair>,
+    /// Logins are skipped until this time after the server rejects the credentials.
+    login_blocked_until: Mutex<Option<Instant>>,
+    /// Bounds the number of game states being fetched at once.
+    game_fetches: Semaphore,
     config: Config,
 }
 
 impl SlobsterbleClient {
 
-    /// Initialize a new client but with expired JWTs.
-    pub fn new(config: Config) -> SlobsterbleClient {
-        let client = reqwest::blocking::Client::new();
-        let tokens = TokenPair::default();
-        SlobsterbleClient{ client, tokens, config }
+    /// Initialize a new client with the tokens saved by a previous run.
+    ///
+    /// The tokens are expired if there is no token file or it cannot be read. Fail if the proxy url
+    /// is invalid or the root certificate cannot be loaded.
+    pub fn new(config: Config) -> Result<SlobsterbleClient, String> {
+        // The connection is kept alive between polls as long as the poll interval is shorter than
+        // the pool idle timeout.
+        let mut client_builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_seconds))
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_seconds));
+        if let Some(proxy_url) = &config.proxy_url {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| format!("Invalid proxy url {}: {}", proxy_url, e))?;
+            client_builder = client_builder.proxy(proxy);
+        }
+        if let Some(ca_cert_path) = &config.ca_cert_path {
+            client_builder = client_builder.add_root_certificate(load_certificate(ca_cert_path)?);
+        }
+        let client = client_builder.build().map_err(|e| format!("Failed to build the HTTP client: {}", e))?;
+        let tokens = match &config.token_path {
+            Some(token_path) => TokenPair::load(token_path).unwrap_or_else(|err| {
+                tracing::warn!("{}", err);
+                TokenPair::default()
+            }),
+            None => TokenPair::default(),
+        };
+        let game_fetches = Semaphore::new(config.max_concurrent_requests);
+        Ok(SlobsterbleClient{
+            client, tokens: Mutex::new(tokens), login_blocked_until: Mutex::new(None), game_fetches, config
+        })
     }
 
     /// Get a list of active or recently completed games for the player.
     ///
     /// This function may update the refresh and access tokens.
-    pub fn list_games(&mut self) -> Result<Vec<GameInfo>, reqwest::Error> {
-        if self.tokens.get_access_token_ref().is_almost_expired() {
-            self.renew_access_token(false);
-        }
-        let mut games_path = String::from(&self.config.root_url);
-        games_path.push_str("api/games");
-        let request = self.client.get(games_path)
-            .header(AUTHORIZATION, self.get_access_auth_header());
-        let response = request.send()?;
-        match response.error_for_status() {
-            Ok(response) => {
-                response.json::<Vec<GameInfo>>()
-            },
-            Err(err) => Err(err),
-        }
+    pub async fn list_games(&self) -> Result<Vec<GameInfo>, reqwest::Error> {
+        let games_path = self.endpoint_url(&self.config.api_paths.games, "");
+        let auth_header = self.get_access_auth_header().await;
+        let games_query = self.games_query();
+        let response = self.send_with_retries(|| {
+            self.client.get(&games_path).query(&games_query).header(AUTHORIZATION, &auth_header)
+        }).await?;
+        response.json::<Vec<GameInfo>>().await
+    }
+
+    /// Wait for the games list to change, by asking the server to hold the games list response
+    /// for up to the given number of seconds.
+    ///
+    /// Return false if the server does not mark the response as long polled, since then it has
+    /// answered at once and the caller should poll at a fixed interval instead.
+    pub async fn wait_for_games(&self, wait_seconds: u64) -> Result<bool, reqwest::Error> {
+        let games_path = self.endpoint_url(&self.config.api_paths.games, "");
+        let auth_header = self.get_access_auth_header().await;
+        let timeout = Duration::from_secs(wait_seconds + self.config.request_timeout_seconds);
+        let response = self.send_with_retries(|| {
+            self.client.get(&games_path)
+                .query(&[("wait", wait_seconds)])
+                .timeout(timeout)
+                .header(AUTHORIZATION, &auth_header)
+        }).await?;
+        Ok(response.headers().contains_key(LONG_POLL_HEADER))
     }
 
     /// Get the game state for the specified game.
     ///
     /// This function may update the refresh and access tokens.
-    pub fn get_game(&mut self, game_id: &str) -> Result<GameSerializer, reqwest::Error> {
-        let mut game_path = String::from(&self.config.root_url);
-        game_path.push_str("api/game/");
-        game_path.push_str(game_id);
-        if self.tokens.get_access_token_ref().is_almost_expired() {
-            self.renew_access_token(false);
-        }
-        let request = self.client.get(game_path)
-            .header(AUTHORIZATION, self.get_access_auth_header());
-        let response = request.send()?;
-        match response.error_for_status() {
-            Ok(response) => response.json::<GameSerializer>(),
-            Err(err) => Err(err),
-        }
+    ///
+    /// At most `max_concurrent_requests` game states are fetched at once, and any other fetches
+    /// wait for one of those to finish.
+    pub async fn get_game(&self, game_id: &str) -> Result<GameSerializer, reqwest::Error> {
+        let _permit = self.game_fetches.acquire().await.expect("The game fetch semaphore is never closed.");
+        let game_path = self.endpoint_url(&self.config.api_paths.game, game_id);
+        let auth_header = self.get_access_auth_header().await;
+        let response = self.send_with_retries(|| {
+            self.client.get(&game_path).header(AUTHORIZATION, &auth_header)
+        }).await?;
+        response.json::<GameSerializer>().await
     }
 
-    pub fn play_turn(&mut self, game_id: &str, played_tiles: &Vec<FlatPlayedTileSerializer>) -> Result<String, reqwest::Error> {
-        let mut game_path = String::from(&self.config.root_url);
-        game_path.push_str("api/game/");
-        game_path.push_str(game_id);
-        if self.tokens.get_access_token_ref().is_almost_expired() {
-            self.renew_access_token(false);
-        }
-        let request = self.client.post(game_path)
-            .header(AUTHORIZATION, self.get_access_auth_header())
-            .json(&played_tiles);
-        let response = request.send()?;
-        match response.error_for_status() {
-            Ok(response) => response.text(),
-            Err(err) => Err(err),
+    /// Play the tiles in the specified game.
+    ///
+    /// If the server refuses the play as invalid, the error includes the reason it gave. Other
+    /// client errors, such as an expired token or a rate limit, are request errors.
+    pub async fn play_turn(&self, game_id: &str, played_tiles: &Vec<FlatPlayedTileSerializer>) -> Result<String, TurnError> {
+        let game_path = self.endpoint_url(&self.config.api_paths.play, game_id);
+        let auth_header = self.get_access_auth_header().await;
+        let response = self.send_allowing_rejections(|| {
+            self.client.post(&game_path)
+                .header(AUTHORIZATION, &auth_header)
+                .json(&played_tiles)
+        }).await.map_err(TurnError::Request)?;
+        let status = response.status();
+        let text = response.text().await.map_err(TurnError::Request)?;
+        if is_rejection(status) {
+            return Err(TurnError::Rejected { status, reason: text });
         }
+        Ok(text)
+    }
+
+    /// Exchange the given tiles from the rack for new tiles from the bag.
+    pub async fn exchange_tiles(&self, game_id: &str, exchanged_tiles: &Vec<FlatPlayedTileSerializer>) -> Result<String, reqwest::Error> {
+        let exchange_path = self.endpoint_url(&self.config.api_paths.exchange, game_id);
+        let auth_header = self.get_access_auth_header().await;
+        let response = self.send_with_retries(|| {
+            self.client.post(&exchange_path)
+                .header(AUTHORIZATION, &auth_header)
+                .json(&exchanged_tiles)
+        }).await?;
+        response.text().await
+    }
+
+    /// Pass the turn without playing or exchanging any tiles.
+    pub async fn pass_turn(&self, game_id: &str) -> Result<String, reqwest::Error> {
+        let pass_path = self.endpoint_url(&self.config.api_paths.pass, game_id);
+        let auth_header = self.get_access_auth_header().await;
+        let no_tiles: Vec<FlatPlayedTileSerializer> = Vec::new();
+        let response = self.send_with_retries(|| {
+            self.client.post(&pass_path)
+                .header(AUTHORIZATION, &auth_header)
+                .json(&no_tiles)
+        }).await?;
+        response.text().await
+    }
+
+    /// Resign from the game, which ends it.
+    pub async fn resign(&self, game_id: &str) -> Result<String, reqwest::Error> {
+        let resign_path = self.endpoint_url(&self.config.api_paths.resign, game_id);
+        let auth_header = self.get_access_auth_header().await;
+        let response = self.send_with_retries(|| {
+            self.client.post(&resign_path)
+                .header(AUTHORIZATION, &auth_header)
+        }).await?;
+        response.text().await
+    }
+
+    /// Return true iff the refresh token has not expired, so that requests can be authenticated
+    /// without logging in again.
+    pub async fn is_authenticated(&self) -> bool {
+        !self.tokens.lock().await.get_refresh_token_ref().is_expired()
     }
 
     /// Renew the refresh token for the client if it has expired or will expire soon.
-    pub fn renew_refresh_token(&mut self, force: bool) {
-        if !self.tokens.get_refresh_token_ref().is_almost_expired() && !force {
-            ()
-        }
-        let tokens = self.get_new_refresh_token();
-        match tokens {
-            Ok(tokens) => {
-                self.tokens = tokens;
-                ()
+    ///
+    /// If the server has rejected the credentials, no login is attempted until the backoff ends.
+    async fn renew_refresh_token(&self, tokens: &mut TokenPair, force: bool) {
+        if !tokens.get_refresh_token_ref().is_almost_expired() && !force {
+            return;
+        }
+        let mut login_blocked_until = self.login_blocked_until.lock().await;
+        if login_blocked_until.is_some_and(|blocked_until| Instant::now() < blocked_until) {
+            tracing::debug!("Skipping login for {} since the credentials were rejected.", self.config.auth_data.username);
+            return;
+        }
+        match self.get_new_refresh_token().await {
+            Ok(new_tokens) => {
+                *tokens = new_tokens;
+                *login_blocked_until = None;
+                self.save_tokens(tokens);
+            },
+            Err(err @ AuthError::CredentialsRejected { .. }) => {
+                tracing::error!(
+                    "{} Not logging in as {} again for {} minutes.",
+                    err, self.config.auth_data.username, REJECTED_CREDENTIALS_BACKOFF.as_secs() / 60
+                );
+                *login_blocked_until = Some(Instant::now() + REJECTED_CREDENTIALS_BACKOFF);
             },
             Err(err) => {
-                log::error!("Failed to renew refresh token: {}", err);
-                ()
+                tracing::error!("Failed to renew refresh token: {}", err);
             }
         }
     }
 
     /// Get a new refresh token, access token pair.
-    fn get_new_refresh_token(&self) -> Result<TokenPair, reqwest::Error> {
-        let mut auth_path = String::from(&self.config.root_url);
-        auth_path.push_str("api/login");
+    async fn get_new_refresh_token(&self) -> Result<TokenPair, AuthError> {
+        let auth_path = self.endpoint_url(&self.config.api_paths.login, "");
         let mut map = HashMap::new();
         map.insert("username", &self.config.auth_data.username);
         map.insert("password", &self.config.auth_data.password);
-        let response = self.client.post(auth_path).json(&map).send()?;
-        match response.error_for_status() {
-            Ok(response) => {
-                response.json::<TokenPair>()
-            },
-            Err(err) => Err(err),
-        }
+        let response = self.send_with_retries(|| self.client.post(&auth_path).json(&map)).await?;
+        Ok(response.json::<TokenPair>().await?)
     }
 
     /// Renew the access token if it is expired or will expire soon.
-    fn renew_access_token(&mut self, force: bool) {
-        if !self.tokens.get_access_token_ref().is_almost_expired() && !force {
-            ()
+    async fn renew_access_token(&self, tokens: &mut TokenPair, force: bool) {
+        if !tokens.get_access_token_ref().is_almost_expired() && !force {
+            return;
         }
-        if self.tokens.get_refresh_token_ref().is_almost_expired() {
-            self.renew_refresh_token(true)
+        if tokens.get_refresh_token_ref().is_almost_expired() {
+            // Logging in gives a new access token along with the new refresh token.
+            self.renew_refresh_token(tokens, true).await;
+            if !tokens.get_access_token_ref().is_almost_expired() {
+                return;
+            }
+            // The login failed. An expired refresh token cannot be used to refresh the access token.
+            if tokens.get_refresh_token_ref().is_expired() {
+                return;
+            }
         }
-        let access_token = self.get_new_access_token();
-        match access_token {
+        match self.get_new_access_token(tokens.get_refresh_token_ref()).await {
             Ok(access_token) => {
-                let tokens = TokenPair::new(self.tokens.get_refresh_token_ref().clone(), access_token);
-                self.tokens = tokens;
-                ()
+                *tokens = TokenPair::new(tokens.get_refresh_token_ref().clone(), access_token);
+                self.save_tokens(tokens);
             },
             Err(err) => {
-                log::error!("Failed to renew access token: {}", err);
-                self.renew_refresh_token(true);
-                ()
+                tracing::error!("Failed to renew access token: {}", err);
+                self.renew_refresh_token(tokens, true).await;
+            }
+        }
+    }
+
+    /// Save the tokens so that the next run can reuse them.
+    fn save_tokens(&self, tokens: &TokenPair) {
+        if let Some(token_path) = &self.config.token_path {
+            if let Err(err) = tokens.save(token_path) {
+                tracing::error!("{}", err);
             }
         }
     }
 
     /// Get a new access token.
-    fn get_new_access_token(&self) -> Result<Token, reqwest::Error> {
-        let mut renew_path = String::from(&self.config.root_url);
-        renew_path.push_str("api/refresh-access");
-        let request = self.client
-            .post(renew_path)
-            .header(AUTHORIZATION, self.get_refresh_auth_header());
-        let response = request.send()?;
-        match response.error_for_status() {
-            Ok(response) => {
-                response.json::<Token>()
-            },
-            Err(err) => Err(err),
+    async fn get_new_access_token(&self, refresh_token: &Token) -> Result<Token, reqwest::Error> {
+        let renew_path = self.endpoint_url(&self.config.api_paths.refresh, "");
+        let auth_header = format!("Bearer {}", refresh_token.token());
+        let response = self.send_with_retries(|| {
+            self.client.post(&renew_path).header(AUTHORIZATION, &auth_header)
+        }).await?;
+        response.json::<Token>().await
+    }
+
+    /// Send a request, retrying with exponential backoff if it fails in a way that may be temporary.
+    ///
+    /// The request is rebuilt for each attempt. If every attempt fails, the last error is returned.
+    /// Requests that are not idempotent, such as plays, are only retried when the server cannot
+    /// have acted on them, so that a retry never makes a move twice.
+    async fn send_with_retries<F>(&self, build_request: F) -> Result<reqwest::Response, reqwest::Error>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        self.send_allowing_rejections(build_request).await?.error_for_status()
+    }
+
+    /// Send a request like `send_with_retries`, except that a response in which the server rejects
+    /// the request as invalid is returned rather than converted to an error, so that its body can
+    /// be read.
+    async fn send_allowing_rejections<F>(&self, build_request: F) -> Result<reqwest::Response, reqwest::Error>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let request = build_request().build()?;
+            let idempotent = request.method().is_idempotent();
+            let result = match self.client.execute(request).await {
+                Ok(response) if is_rejection(response.status()) => Ok(response),
+                Ok(response) => response.error_for_status(),
+                Err(err) => Err(err),
+            };
+            match result {
+                Err(err) if attempt < self.config.request_max_attempts && is_retryable(&err, idempotent) => {
+                    let delay_ms = self.config.request_retry_base_delay_ms * 2u64.pow(attempt - 1);
+                    tracing::warn!("Request failed on attempt {}, retrying in {} ms: {}", attempt, delay_ms, err);
+                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Get the query parameters for listing games, which ask the server to list only the games
+    /// where it is the fetching player's turn if the config says to.
+    fn games_query(&self) -> Vec<(&'static str, &'static str)> {
+        if self.config.filter_games_by_turn {
+            vec![("whose_turn", "me")]
+        } else {
+            Vec::new()
         }
     }
 
+    /// Build the full url for an API path template.
+    fn endpoint_url(&self, path_template: &str, game_id: &str) -> String {
+        let mut url = String::from(&self.config.root_url);
+        url.push_str(&path_template.replace("{game_id}", game_id));
+        url
+    }
+
     /// Get the authorization header using the access token.
-    fn get_access_auth_header(&self) -> String {
+    ///
+    /// The tokens are renewed first if they are expired or will expire soon. Holding the lock
+    /// while renewing means concurrent requests wait for a single renewal.
+    async fn get_access_auth_header(&self) -> String {
+        let mut tokens = self.tokens.lock().await;
+        self.renew_access_token(&mut tokens, false).await;
         let mut auth_header = String::from("Bearer ");
-        auth_header.push_str(&self.tokens.get_access_token_ref().token());
+        auth_header.push_str(tokens.get_access_token_ref().token());
         auth_header
     }
+}
 
-    /// Get the authorization header using the refresh token.
-    fn get_refresh_auth_header(&self) -> String {
-        let mut auth_header = String::from("Bearer ");
-        auth_header.push_str(&self.tokens.get_refresh_token_ref().token());
-        auth_header
+/// Load a PEM encoded root certificate.
+fn load_certificate(path: &str) -> Result<reqwest::Certificate, String> {
+    let pem = std::fs::read(path).map_err(|e| format!("Failed to read certificate {}: {}", path, e))?;
+    reqwest::Certificate::from_pem(&pem).map_err(|e| format!("Failed to parse certificate {}: {}", path, e))
+}
+
+/// Return true iff the error is a timeout, a connection failure, a rate limit, or a server error.
+///
+/// Client errors such as a failed login are not retried since they will fail again. For a request
+/// that is not idempotent, only a connection failure or a rate limit is retried, since after a
+/// timeout or a server error the request may already have taken effect.
+fn is_retryable(err: &reqwest::Error, idempotent: bool) -> bool {
+    let rate_limited = err.status() == Some(StatusCode::TOO_MANY_REQUESTS);
+    if !idempotent {
+        return err.is_connect() || rate_limited;
+    }
+    err.is_timeout() || err.is_connect() || rate_limited || err.status().is_some_and(|status| status.is_server_error())
+}
+
+/// Return true iff the status is one the server gives when it judges a request to be invalid,
+/// as opposed to failing to handle it.
+fn is_rejection(status: StatusCode) -> bool {
+    status == StatusCode::BAD_REQUEST || status == StatusCode::UNPROCESSABLE_ENTITY
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use crate::utilities::tests::TempPath;
+
+    #[test]
+    fn test_default_endpoint_urls() {
+        let client = SlobsterbleClient::new(Config::for_tests("")).unwrap();
+        let api_paths = &client.config.api_paths;
+        assert_eq!(client.endpoint_url(&api_paths.login, ""), "http://127.0.0.1:5000/api/login");
+        assert_eq!(client.endpoint_url(&api_paths.games, ""), "http://127.0.0.1:5000/api/games");
+        assert_eq!(client.endpoint_url(&api_paths.game, "12"), "http://127.0.0.1:5000/api/game/12");
+        assert_eq!(client.endpoint_url(&api_paths.play, "12"), "http://127.0.0.1:5000/api/game/12");
+        assert_eq!(client.endpoint_url(&api_paths.resign, "12"), "http://127.0.0.1:5000/api/game/12/resign");
+    }
+
+    #[test]
+    fn test_overridden_endpoint_urls() {
+        let client = SlobsterbleClient::new(Config::for_tests(
+            "[slobsterble]\nlogin_path=auth/login\nplay_path=api/game/{game_id}/play\n\
+            exchange_path=api/game/{game_id}/exchange\npass_path=api/v2/pass?game={game_id}\n"
+        )).unwrap();
+        let api_paths = &client.config.api_paths;
+        assert_eq!(client.endpoint_url(&api_paths.login, ""), "http://127.0.0.1:5000/auth/login");
+        assert_eq!(client.endpoint_url(&api_paths.play, "12"), "http://127.0.0.1:5000/api/game/12/play");
+        assert_eq!(client.endpoint_url(&api_paths.exchange, "12"), "http://127.0.0.1:5000/api/game/12/exchange");
+        assert_eq!(client.endpoint_url(&api_paths.pass, "12"), "http://127.0.0.1:5000/api/v2/pass?game=12");
+        // Paths that are not overridden keep their defaults.
+        assert_eq!(client.endpoint_url(&api_paths.game, "12"), "http://127.0.0.1:5000/api/game/12");
+    }
+
+    #[tokio::test]
+    async fn test_fresh_tokens_are_not_renewed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = SlobsterbleClient::new(Config::for_tests(
+            &format!("[slobsterble]\nroot_url=http://127.0.0.1:{}/\n", port)
+        )).unwrap();
+        let far_future = chrono::Utc::now() + chrono::Duration::days(365);
+        let mut tokens = TokenPair::new(
+            Token::for_tests("refresh", far_future), Token::for_tests("access", far_future)
+        );
+        client.renew_access_token(&mut tokens, false).await;
+        client.renew_refresh_token(&mut tokens, false).await;
+        assert_eq!(tokens.get_access_token_ref().token(), "access");
+        assert_eq!(tokens.get_refresh_token_ref().token(), "refresh");
+        // Nothing tried to connect to the server.
+        assert_eq!(listener.accept().unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_loaded_from_disk() {
+        let path = TempPath::new("tokens.json");
+        let config = Config::for_tests(&format!("token_path={}\n", path.as_str()));
+        // A missing token file starts with expired tokens.
+        let client = SlobsterbleClient::new(config.clone()).unwrap();
+        assert!(client.tokens.lock().await.get_access_token_ref().is_almost_expired());
+
+        let far_future = chrono::Utc::now() + chrono::Duration::days(365);
+        let tokens = TokenPair::new(Token::for_tests("refresh", far_future), Token::for_tests("access", far_future));
+        tokens.save(path.as_str()).unwrap();
+        let client = SlobsterbleClient::new(config.clone()).unwrap();
+        assert_eq!(client.tokens.lock().await.get_access_token_ref().token(), "access");
+        assert!(!client.tokens.lock().await.get_refresh_token_ref().is_almost_expired());
+
+        // A corrupt token file falls back to expired tokens.
+        std::fs::write(&path, "not json").unwrap();
+        let client = SlobsterbleClient::new(config).unwrap();
+        assert!(client.tokens.lock().await.get_refresh_token_ref().is_almost_expired());
+    }
+
+    #[tokio::test]
+    async fn test_hung_server_times_out() {
+        // The server accepts the connection but never responds.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let root_url = format!("http://{}/", listener.local_addr().unwrap());
+        let client = test_client(&root_url, "request_max_attempts=1\nrequest_timeout_seconds=1\n");
+        let err = client.list_games().await.unwrap_err();
+        assert!(err.is_timeout());
+        drop(listener);
+    }
+
+    /// Serve one response with each status code, in order, and return the root url.
+    pub(crate) fn serve_statuses(statuses: Vec<u16>) -> (String, thread::JoinHandle<()>) {
+        serve_responses(statuses.into_iter().map(|status| (status, "[]")).collect())
+    }
+
+    /// Serve one response with each status code and body, in order, and return the root url.
+    pub(crate) fn serve_responses(responses: Vec<(u16, &'static str)>) -> (String, thread::JoinHandle<()>) {
+        serve_with_headers(responses.into_iter().map(|(status, body)| (status, "", body)).collect())
+    }
+
+    /// Serve one response with each status code, extra header lines and body, in order, and
+    /// return the root url.
+    pub(crate) fn serve_with_headers(responses: Vec<(u16, &'static str, &'static str)>) -> (String, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let root_url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            for (status, headers, body) in responses {
+                let (mut stream, _address) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                write!(
+                    stream, "HTTP/1.1 {} Status\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, headers, body.len(), body
+                ).unwrap();
+            }
+        });
+        (root_url, handle)
+    }
+
+    fn retrying_client(root_url: &str) -> SlobsterbleClient {
+        test_client(root_url, "request_max_attempts=3\nrequest_retry_base_delay_ms=1\n")
+    }
+
+    /// Build a client for the server with fresh tokens and the extra settings.
+    pub(crate) fn test_client(root_url: &str, extra_settings: &str) -> SlobsterbleClient {
+        let mut client = SlobsterbleClient::new(Config::for_tests(&format!(
+            "{}[slobsterble]\nroot_url={}\n", extra_settings, root_url
+        ))).unwrap();
+        let far_future = chrono::Utc::now() + chrono::Duration::days(365);
+        let tokens = TokenPair::new(Token::for_tests("refresh", far_future), Token::for_tests("access", far_future));
+        client.tokens = Mutex::new(tokens);
+        client
+    }
+
+    /// Build a client for the server whose access token has expired, so that its next request
+    /// first renews the access token.
+    pub(crate) fn expired_access_client(root_url: &str) -> SlobsterbleClient {
+        let mut client = test_client(root_url, "");
+        let far_future = chrono::Utc::now() + chrono::Duration::days(365);
+        let past = chrono::Utc::now() - chrono::Duration::days(1);
+        let tokens = TokenPair::new(Token::for_tests("refresh", far_future), Token::for_tests("access", past));
+        client.tokens = Mutex::new(tokens);
+        client
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        let (root_url, handle) = serve_statuses(vec![503, 500, 200]);
+        let client = retrying_client(&root_url);
+        assert!(client.list_games().await.unwrap().is_empty());
+        handle.join().unwrap();
+
+        // The last error is returned once the attempts run out.
+        let (root_url, handle) = serve_statuses(vec![503, 503, 502]);
+        let client = retrying_client(&root_url);
+        let err = client.list_games().await.unwrap_err();
+        assert_eq!(err.status().unwrap().as_u16(), 502);
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_game_fetches_are_limited() {
+        let (root_url, handle) = serve_statuses(vec![404, 404, 404]);
+        let client = test_client(&root_url, "max_concurrent_requests=1\n");
+        assert_eq!(client.game_fetches.available_permits(), 1);
+        let results = futures::future::join_all(["1", "2", "3"].iter().map(|game_id| client.get_game(game_id))).await;
+        assert!(results.iter().all(|result| matches!(result, Err(err) if err.status().unwrap().as_u16() == 404)));
+        assert_eq!(client.game_fetches.available_permits(), 1);
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let (root_url, handle) = serve_statuses(vec![401]);
+        let client = retrying_client(&root_url);
+        let err = client.list_games().await.unwrap_err();
+        assert_eq!(err.status().unwrap().as_u16(), 401);
+        handle.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_credentials_block_logins() {
+        let (root_url, handle) = serve_statuses(vec![401, 503]);
+        let client = test_client(&root_url, "request_max_attempts=1\n");
+        assert!(matches!(
+            client.get_new_refresh_token().await,
+            Err(AuthError::CredentialsRejected { status: StatusCode::UNAUTHORIZED })
+        ));
+        assert!(matches!(client.get_new_refresh_token().await, Err(AuthError::Transient(_))));
+        handle.join().unwrap();
+
+        let (root_url, handle) = serve_statuses(vec![401]);
+        let client = test_client(&root_url, "request_max_attempts=1\n");
+        let mut tokens = TokenPair::default();
+        client.renew_refresh_token(&mut tokens, true).await;
+        handle.join().unwrap();
+        assert!(client.login_blocked_until.lock().await.is_some());
+
+        // A second renewal does not try to log in again.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let mut client = test_client(&format!("http://{}/", listener.local_addr().unwrap()), "");
+        client.login_blocked_until = Mutex::new(Some(Instant::now() + REJECTED_CREDENTIALS_BACKOFF));
+        client.renew_refresh_token(&mut tokens, true).await;
+        assert!(tokens.get_refresh_token_ref().is_almost_expired());
+        assert_eq!(listener.accept().unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        // The root url is never connected to directly, so the proxy serves the response.
+        let (proxy_url, handle) = serve_statuses(vec![200]);
+        let client = test_client("http://slobsterble.invalid/", &format!(
+            "request_max_attempts=1\n[slobsterble]\nproxy_url={}\n", proxy_url
+        ));
+        assert!(client.list_games().await.unwrap().is_empty());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_proxy_or_certificate_is_an_error() {
+        let path = TempPath::new("missing_cert.pem");
+        let err = SlobsterbleClient::new(Config::for_tests(&format!("[slobsterble]\nca_cert_path={}\n", path.as_str())))
+            .unwrap_err();
+        assert!(err.contains(path.as_str()));
+        let err = SlobsterbleClient::new(Config::for_tests("[slobsterble]\nproxy_url=not a url\n")).unwrap_err();
+        assert!(err.contains("not a url"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_play_gives_reason() {
+        let (root_url, handle) = serve_responses(vec![(400, "Invalid words: ZZZ"), (503, ""), (200, "OK")]);
+        let client = retrying_client(&root_url);
+        match client.play_turn("12", &Vec::new()).await {
+            Err(TurnError::Rejected { status, reason }) => {
+                assert_eq!(status, StatusCode::BAD_REQUEST);
+                assert_eq!(reason, "Invalid words: ZZZ");
+            },
+            result => panic!("Expected a rejected play, got {:?}", result),
+        }
+        // The play may have been made despite a server error, so it is not retried.
+        match client.play_turn("12", &Vec::new()).await {
+            Err(TurnError::Request(err)) => assert_eq!(err.status(), Some(StatusCode::SERVICE_UNAVAILABLE)),
+            result => panic!("Expected a request error, got {:?}", result),
+        }
+        assert_eq!(client.play_turn("12", &Vec::new()).await.unwrap(), "OK");
+        handle.join().unwrap();
+
+        // Auth failures and rate limits are not judgements of the play.
+        let (root_url, handle) = serve_responses(vec![(401, "Expired token"), (429, ""), (422, "Not a word")]);
+        let client = retrying_client(&root_url);
+        match client.play_turn("12", &Vec::new()).await {
+            Err(TurnError::Request(err)) => assert_eq!(err.status(), Some(StatusCode::UNAUTHORIZED)),
+            result => panic!("Expected a request error, got {:?}", result),
+        }
+        assert!(matches!(
+            client.play_turn("12", &Vec::new()).await,
+            Err(TurnError::Rejected { status: StatusCode::UNPROCESSABLE_ENTITY, .. })
+        ));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_games_query() {
+        let client = SlobsterbleClient::new(Config::for_tests("")).unwrap();
+        assert!(client.games_query().is_empty());
+        let client = SlobsterbleClient::new(Config::for_tests("[slobsterble]\nfilter_games_by_turn=true\n")).unwrap();
+        assert_eq!(client.games_query(), vec![("whose_turn", "me")]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_games() {
+        let (root_url, handle) = serve_with_headers(vec![(200, "X-Long-Poll: 1\r\n", "[]"), (200, "", "[]")]);
+        let client = test_client(&root_url, "");
+        assert!(client.wait_for_games(30).await.unwrap());
+        // A server that ignores the wait answers without the header.
+        assert!(!client.wait_for_games(30).await.unwrap());
+        handle.join().unwrap();
     }
 }
@@ -1,5 +1,11 @@
+/// Get the combination of indices that follows the selection in lexicographic order, if any.
+///
+/// The empty selection is the only combination of size zero, so it has no next combination.
 pub fn next_combination(mut selection: Vec<usize>, population_size: usize) -> Option<Vec<usize>> {
     let selection_size = selection.len();
+    if selection_size == 0 {
+        return None;
+    }
     if population_size < selection_size {
         panic!("Cannot get the next combination for a selection size smaller than the population size.");
     }
@@ -17,80 +23,62 @@ pub fn next_combination(mut selection: Vec<usize>, population_size: usize) -> Op
     Some(selection)
 }
 
-pub fn next_permutation(mut permutation: Vec<usize>) -> Option<Vec<usize>> {
-    let mut first = get_first(&permutation)?;
-    let mut to_swap = permutation.len() - 1;
-    while permutation[first] >= permutation[to_swap] {
-        to_swap -= 1;
-    }
-    swap(&mut permutation, first, to_swap);
-    first += 1;
-    to_swap = permutation.len() - 1;
-    while first < to_swap {
-        swap(&mut permutation, first, to_swap);
-        first += 1;
-        to_swap -= 1;
-    }
-    Some(permutation)
-}
-fn get_first(permutation: &Vec<usize>) -> Option<usize> {
-    if permutation.len() == 1 {
-        return None;
-    }
-    for index in (0..permutation.len() - 1).rev() {
-        if permutation[index] < permutation[index + 1] {
-            return Some(index);
-        }
-    }
-    None
-}
-fn swap(permutation: &mut Vec<usize>, i: usize, j: usize) {
-    let tmp = permutation[i];
-    permutation[i] = permutation[j];
-    permutation[j] = tmp;
+/// Get the index of the first occurrence of each distinct item, in order.
+///
+/// A search that branches on each item can branch on these indices alone, since an item equal to
+/// an earlier one would repeat the same branch.
+pub fn first_occurrences<T: PartialEq>(items: &[T]) -> Vec<usize> {
+    (0..items.len())
+        .filter(|index| !items[..*index].contains(&items[*index]))
+        .collect()
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
 
-    #[test]
-    fn test_next_permutation() {
-        let mut permutation = Some(vec![0, 1, 2, 3]);
-        let mut expected_permutations = [
-            Some(vec![0, 1, 2, 3]),
-            Some(vec![0, 1, 3, 2]),
-            Some(vec![0, 2, 1, 3]),
-            Some(vec![0, 2, 3, 1]),
-            Some(vec![0, 3, 1, 2]),
-            Some(vec![0, 3, 2, 1]),
-            Some(vec![1, 0, 2, 3]),
-            Some(vec![1, 0, 3, 2]),
-            Some(vec![1, 2, 0, 3]),
-            Some(vec![1, 2, 3, 0]),
-            Some(vec![1, 3, 0, 2]),
-            Some(vec![1, 3, 2, 0]),
-            Some(vec![2, 0, 1, 3]),
-            Some(vec![2, 0, 3, 1]),
-            Some(vec![2, 1, 0, 3]),
-            Some(vec![2, 1, 3, 0]),
-            Some(vec![2, 3, 0, 1]),
-            Some(vec![2, 3, 1, 0]),
-            Some(vec![3, 0, 1, 2]),
-            Some(vec![3, 0, 2, 1]),
-            Some(vec![3, 1, 0, 2]),
-            Some(vec![3, 1, 2, 0]),
-            Some(vec![3, 2, 0, 1]),
-            Some(vec![3, 2, 1, 0]),
-            None,
-        ];
-        assert_eq!(permutation, expected_permutations[0]);
-        for index in 1..25 {
-            permutation = next_permutation(permutation.unwrap());
-            assert_eq!(permutation, expected_permutations[index]);
+    /// A path in the temporary directory for a single test, which is deleted when dropped.
+    pub(crate) struct TempPath(PathBuf);
+
+    impl TempPath {
+
+        /// Get a temporary path for the test with the name, removing anything an earlier run left there.
+        pub(crate) fn new(name: &str) -> TempPath {
+            let temp_path = TempPath(std::env::temp_dir().join(format!("aislobsterble_{}_{}", std::process::id(), name)));
+            temp_path.remove();
+            temp_path
+        }
+
+        pub(crate) fn as_str(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+
+        fn remove(&self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    impl AsRef<Path> for TempPath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            self.remove();
         }
     }
 
+    fn binomial(n: usize, k: usize) -> usize {
+        (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+    }
+
     #[test]
     fn test_next_combination() {
         let mut combination = Some(vec![0, 1, 2, 3]);
@@ -119,4 +107,45 @@ mod tests {
             assert_eq!(combination, expected_combinations[index]);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_empty_inputs() {
+        assert_eq!(next_combination(Vec::new(), 0), None);
+        assert_eq!(next_combination(Vec::new(), 5), None);
+        assert_eq!(first_occurrences::<char>(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_first_occurrences() {
+        assert_eq!(first_occurrences(&['A', 'A', 'E', 'E', 'I', 'O', 'U']), vec![0, 2, 4, 5, 6]);
+        assert_eq!(first_occurrences(&['Q', 'A', 'Q', 'A']), vec![0, 1]);
+        assert_eq!(first_occurrences(&['S']), vec![0]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_first_occurrences_cover_each_distinct_item(items in prop::collection::vec(0usize..4, 0..=8)) {
+            let indices = first_occurrences(&items);
+            let selected: Vec<usize> = indices.iter().map(|index| items[*index]).collect();
+            prop_assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+            prop_assert_eq!(selected.iter().collect::<HashSet<_>>().len(), selected.len());
+            prop_assert_eq!(selected.iter().collect::<HashSet<_>>(), items.iter().collect::<HashSet<_>>());
+        }
+
+        #[test]
+        fn test_next_combination_visits_every_combination(
+            (population_size, selection_size) in (1usize..=8).prop_flat_map(|n| (Just(n), 1..=n))
+        ) {
+            let mut combination = Some((0..selection_size).collect::<Vec<usize>>());
+            let mut seen = Vec::new();
+            while let Some(current) = combination {
+                prop_assert!(current.windows(2).all(|pair| pair[0] < pair[1]));
+                prop_assert!(current.iter().all(|index| *index < population_size));
+                seen.push(current.clone());
+                combination = next_combination(current, population_size);
+            }
+            prop_assert_eq!(seen.len(), binomial(population_size, selection_size));
+            prop_assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+}